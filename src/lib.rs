@@ -0,0 +1,4237 @@
+//! Extracts metadata from Adobe Flash SWF files.
+//!
+//! [`extract`] reads a SWF into a [`Metadata`] record, and [`format`] renders that record
+//! as JSON, YAML, a property list, CSV or plain text.
+//!
+//! ```no_run
+//! use extract_metadata::{Format, extract};
+//!
+//! let metadata = extract(std::path::Path::new("movie.swf"))?;
+//! println!("{}x{}", metadata.stage_size.0, metadata.stage_size.1);
+//! println!("{}", extract_metadata::format(&metadata, Format::Json));
+//! # Ok::<(), extract_metadata::ExtractError>(())
+//! ```
+
+use sha2::{Digest, Sha256};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Why a SWF couldn't be read
+#[derive(Debug)]
+pub enum ExtractError {
+    Io(std::io::Error),
+    Decompress(swf::error::Error),
+    Parse(swf::error::Error),
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExtractError::Io(error) => write!(f, "failed to read file: {}", error),
+            ExtractError::Decompress(error) => write!(f, "failed to decompress SWF: {}", error),
+            ExtractError::Parse(error) => write!(f, "failed to parse SWF: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ExtractError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExtractError::Io(error) => Some(error),
+            ExtractError::Decompress(error) | ExtractError::Parse(error) => Some(error),
+        }
+    }
+}
+
+impl From<std::io::Error> for ExtractError {
+    fn from(error: std::io::Error) -> Self {
+        ExtractError::Io(error)
+    }
+}
+
+/// Options controlling which optional metadata is collected
+#[derive(Debug, Default)]
+pub struct ExtractOptions {
+    /// Fill in `parse_warnings`, which are only captured while `WarningCollector` is the
+    /// installed logger
+    pub warnings: bool,
+    pub dereference_imports: bool,
+    pub displaylist: bool,
+    pub fonts: bool,
+    pub bitmaps: bool,
+    pub dead_code: bool,
+    pub scripts: bool,
+    pub shapes: bool,
+    pub audio: bool,
+    pub video: bool,
+    pub binary_data: bool,
+    pub buttons: bool,
+    pub classes: bool,
+    pub tags: bool,
+    pub tag_counts: bool,
+    /// With `tag_counts`, also fill in `tag_descriptions`
+    pub describe_tags: bool,
+    pub timings: bool,
+    pub with_schema_version: bool,
+    pub max_sane_fps: Option<f32>,
+    pub normalize_frame_rate: bool,
+    pub trim_prefix: Option<PathBuf>,
+}
+
+/// Record written in place of metadata for a file that failed to extract
+#[derive(serde::Serialize, Debug)]
+struct ErrorPlaceholder<'a> {
+    file_name: &'a str,
+    error: &'a str,
+}
+
+/// Version of the `Metadata` output shape, bumped whenever fields change incompatibly
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The highest frame rate not flagged by `frame_rate_warning` unless `--max-sane-fps` is given
+const DEFAULT_MAX_SANE_FPS: f32 = 120.0;
+
+/// The frame rates `normalized_frame_rate` snaps to
+const COMMON_FRAME_RATES: [f32; 5] = [12.0, 24.0, 25.0, 30.0, 60.0];
+
+/// How far a frame rate may be from a common one and still snap to it
+const FRAME_RATE_TOLERANCE: f32 = 0.1;
+
+/// Everything extracted from one SWF; optional fields are only collected when the
+/// matching `ExtractOptions` flag is set
+#[derive(serde::Serialize, Debug, Default, PartialEq)]
+pub struct Metadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<u32>,
+    pub file_name: String,
+    pub stage_size: (u32, u32),
+    pub stage_size_precise: (f32, f32),
+    pub stage_size_twips: (i32, i32),
+    pub no_of_frames: u32,
+    pub frame_rate: f32,
+    pub metadata_flag_consistent: bool,
+    pub swf_version: u8,
+    pub compression: String,
+    pub file_size_bytes: u64,
+    /// Length of the whole SWF once decompressed, as declared in its header
+    pub uncompressed_size_bytes: u64,
+    /// `uncompressed_size_bytes` over `file_size_bytes`
+    pub compression_ratio: f32,
+    /// SHA-256 of the file as stored, so renamed or re-exported copies can be told apart
+    pub sha256: String,
+    pub background_color: Option<String>,
+    pub actionscript_version: Option<u8>,
+    pub xmp_metadata: Option<String>,
+    pub file_attributes_first: Option<bool>,
+    pub mixed_avm: bool,
+    /// Reserved header bits that are set, which the parser otherwise drops
+    pub header_anomalies: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frame_rate_warning: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub normalized_frame_rate: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parse_warnings: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub broken_imports: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_depth_used: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distinct_depths: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blend_modes: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_as_bitmap_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_cff_fonts: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cff_font_names: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_copyrights: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_glyph_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_bitmap_memory_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bitmap_formats: Option<BTreeMap<String, u32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub empty_sprite_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage_ratio: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reused_sprite_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub init_actions: Option<Vec<u16>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stage_align: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scripted_frame_rates: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_preloader: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_custom_cursor: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clip_event_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clip_event_types: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_offstage_content: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub morph_tween_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uses_nonzero_winding: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sound_frames: Option<Vec<u32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_looping_sound: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub empty_video_streams: Option<Vec<u16>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub binary_blob_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub binary_total_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interactive_button_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub button_urls: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filtered_button_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uses_rsl: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rsl_libraries: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub long_tag_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_version_by_tags: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag_size_histogram: Option<BTreeMap<String, u32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags_after_last_frame: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag_counts: Option<BTreeMap<String, u32>>,
+    /// What each tag type in `tag_counts` holds, keyed the same way; unknown codes have
+    /// no entry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag_descriptions: Option<BTreeMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parse_duration_ms: Option<f32>,
+}
+
+thread_local! {
+    static PARSE_WARNINGS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A `log` logger that collects the recoverable warnings the swf crate reports into
+/// `parse_warnings`.
+///
+/// The library never installs a logger itself, since `log::set_logger` is process-wide.
+/// Hosts that want `parse_warnings` filled in install this one (or forward warn-level
+/// records to it) before extracting:
+///
+/// ```no_run
+/// log::set_logger(&extract_metadata::WarningCollector).unwrap();
+/// log::set_max_level(log::LevelFilter::Warn);
+/// ```
+pub struct WarningCollector;
+
+impl log::Log for WarningCollector {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Warn
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            PARSE_WARNINGS.with(|warnings| warnings.borrow_mut().push(record.args().to_string()));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Extracts metadata from the SWF at `path` without any of the optional fields.
+pub fn extract(path: &Path) -> Result<Metadata, ExtractError> {
+    extract_with_options(path, &ExtractOptions::default())
+}
+
+/// Extracts metadata from the SWF at `path`, collecting the optional fields `options`
+/// enables.
+pub fn extract_with_options(
+    path: &Path,
+    options: &ExtractOptions,
+) -> Result<Metadata, ExtractError> {
+    let file = File::open(path)?;
+    read_metadata(&path.to_string_lossy(), BufReader::new(file), options)
+}
+
+/// The `file_name` `extract_with_options` reports for the SWF at `path`.
+pub fn reported_file_name(path: &Path, options: &ExtractOptions) -> String {
+    let file_name = path.to_string_lossy();
+    match &options.trim_prefix {
+        Some(prefix) => trim_file_name(&file_name, prefix),
+        None => file_name.into_owned(),
+    }
+}
+
+/// Extracts metadata from a SWF held in `reader`, reporting it under `file_name`.
+pub fn read_metadata<R: Read>(
+    file_name: &str,
+    mut reader: R,
+    options: &ExtractOptions,
+) -> Result<Metadata, ExtractError> {
+    PARSE_WARNINGS.with(|warnings| warnings.borrow_mut().clear());
+    // Read everything up front so the stored size is known for any reader, stdin included
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    // Timed from here so slow disks and pipes don't count towards parse_duration_ms
+    let parse_start = Instant::now();
+    let swf_buf = swf::decompress_swf(&data[..]).map_err(ExtractError::Decompress)?;
+    let swf = swf::parse_swf(&swf_buf).map_err(ExtractError::Parse)?;
+    let parse_duration = parse_start.elapsed();
+    let tag_headers = (options.tags || options.tag_counts).then(|| read_tag_headers(&swf_buf.data));
+    let header_anomalies = header_anomalies(&swf_buf.data);
+    let parse_warnings = PARSE_WARNINGS.with(|warnings| warnings.take());
+
+    let mut metadata = build_metadata(file_name, &swf, options);
+    metadata.file_size_bytes = data.len() as u64;
+    let uncompressed_len = swf_buf.header.uncompressed_len() as u64;
+    metadata.uncompressed_size_bytes = uncompressed_len;
+    metadata.compression_ratio = uncompressed_len as f32 / data.len() as f32;
+    metadata.sha256 = Sha256::digest(&data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    if let Some(headers) = tag_headers.as_ref().filter(|_| options.tags) {
+        metadata.long_tag_count =
+            Some(headers.iter().filter(|header| header.is_long).count() as u32);
+        let min_version = headers
+            .iter()
+            .filter_map(|header| swf::TagCode::from_u16(header.code).and_then(tag_min_version))
+            .max();
+        metadata.min_version_by_tags = Some(min_version.unwrap_or(1));
+        metadata.tag_size_histogram = Some(tag_size_histogram(headers));
+        metadata.tags_after_last_frame = Some(tags_after_last_frame(&swf.tags));
+    }
+    if let Some(headers) = tag_headers.as_ref().filter(|_| options.tag_counts) {
+        metadata.tag_counts = Some(tag_counts(headers));
+        if options.describe_tags {
+            metadata.tag_descriptions = Some(tag_descriptions(headers));
+        }
+    }
+    if let Some(prefix) = &options.trim_prefix {
+        metadata.file_name = trim_file_name(file_name, prefix);
+    }
+    if options.warnings {
+        metadata.parse_warnings = Some(parse_warnings);
+    }
+    metadata.header_anomalies = header_anomalies;
+    if options.with_schema_version {
+        metadata.schema_version = Some(SCHEMA_VERSION);
+    }
+    if options.timings {
+        metadata.parse_duration_ms = Some(parse_duration.as_secs_f32() * 1000.0);
+    }
+    if options.dereference_imports {
+        metadata.broken_imports = Some(find_broken_imports(Path::new(file_name), &swf));
+    }
+    Ok(metadata)
+}
+
+/// The record header in front of a tag's payload
+#[derive(Debug)]
+struct TagHeader {
+    code: u16,
+    length: usize,
+    is_long: bool,
+}
+
+/// Walks the raw tag stream, including the tags nested in sprites, and returns each
+/// tag's header.
+///
+/// Headers hold the payload length in 6 bits, so payloads of 63 bytes or more need
+/// the long form. Adobe's encoders always write bitmap tags with long headers, and
+/// some players rely on that; a short-form bitmap header is reported as a warning.
+fn read_tag_headers(data: &[u8]) -> Vec<TagHeader> {
+    let mut headers = Vec::new();
+    read_tag_headers_into(data, &mut headers);
+    headers
+}
+
+fn read_tag_headers_into(mut data: &[u8], headers: &mut Vec<TagHeader>) {
+    while let Some((header, payload, rest)) = next_tag(data) {
+        let tag_code = swf::TagCode::from_u16(header.code);
+        if !header.is_long
+            && matches!(
+                tag_code,
+                Some(
+                    swf::TagCode::DefineBits
+                        | swf::TagCode::DefineBitsJpeg2
+                        | swf::TagCode::DefineBitsJpeg3
+                        | swf::TagCode::DefineBitsJpeg4
+                        | swf::TagCode::DefineBitsLossless
+                        | swf::TagCode::DefineBitsLossless2
+                )
+            )
+        {
+            log::warn!("{:?} tag uses a short header", tag_code.unwrap());
+        }
+        headers.push(header);
+        // DefineSprite payloads are a character id and frame count followed by tags
+        if tag_code == Some(swf::TagCode::DefineSprite) && payload.len() >= 4 {
+            read_tag_headers_into(&payload[4..], headers);
+        }
+        data = rest;
+    }
+}
+
+/// Splits the tag at the start of `data` into its header, its payload and the rest of
+/// the stream, or returns `None` once the stream is exhausted or truncated.
+fn next_tag(data: &[u8]) -> Option<(TagHeader, &[u8], &[u8])> {
+    let code_and_length = u16::from_le_bytes([*data.first()?, *data.get(1)?]);
+    let code = code_and_length >> 6;
+    let mut length = (code_and_length & 0b111111) as usize;
+    let mut header_length = 2;
+    let is_long = length == 0b111111;
+    if is_long {
+        let bytes = data.get(2..6)?;
+        length = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+        header_length = 6;
+    }
+    let payload = data.get(header_length..header_length + length)?;
+    let header = TagHeader {
+        code,
+        length,
+        is_long,
+    };
+    Some((header, payload, &data[header_length + length..]))
+}
+
+/// Describes reserved bits set in the FileAttributes flags, which the `swf` crate
+/// silently drops.
+///
+/// The flags are a 32-bit field of which only bits 0 and 3-6 are defined; a standard
+/// encoder leaves the rest zero, so anything else points at a hand-edited or tampered
+/// file.
+fn header_anomalies(mut data: &[u8]) -> Vec<String> {
+    let mut anomalies = Vec::new();
+    while let Some((header, payload, rest)) = next_tag(data) {
+        if header.code == swf::TagCode::FileAttributes as u16 {
+            let mut bytes = [0; 4];
+            let len = payload.len().min(4);
+            bytes[..len].copy_from_slice(&payload[..len]);
+            let flags = u32::from_le_bytes(bytes);
+            let reserved = flags & !(swf::FileAttributes::all().bits() as u32);
+            if reserved != 0 {
+                anomalies.push(format!(
+                    "FileAttributes reserved bits set: {:#010x}",
+                    reserved
+                ));
+            }
+        }
+        data = rest;
+    }
+    anomalies
+}
+
+/// Counts the root timeline tags after the last `ShowFrame`, which the parser stops short
+/// of `End` on. A file without any `ShowFrame` counts all of its tags.
+fn tags_after_last_frame(tags: &[swf::Tag]) -> u32 {
+    tags.iter()
+        .rev()
+        .take_while(|tag| !matches!(tag, swf::Tag::ShowFrame))
+        .count() as u32
+}
+
+/// Buckets tag payload sizes; every bucket is present so the shape is stable across files.
+fn tag_size_histogram(headers: &[TagHeader]) -> BTreeMap<String, u32> {
+    const BUCKETS: [(usize, &str); 5] = [
+        (64, "0-63"),
+        (256, "64-255"),
+        (1024, "256-1K"),
+        (64 * 1024, "1K-64K"),
+        (usize::MAX, "64K+"),
+    ];
+    let mut histogram: BTreeMap<String, u32> = BUCKETS
+        .iter()
+        .map(|(_, label)| (label.to_string(), 0))
+        .collect();
+    for header in headers {
+        let (_, label) = BUCKETS
+            .iter()
+            .find(|(limit, _)| header.length < *limit)
+            .unwrap_or(&BUCKETS[4]);
+        *histogram.get_mut(*label).unwrap() += 1;
+    }
+    histogram
+}
+
+/// Counts tags, including those nested in sprites, by tag name; codes the parser does
+/// not know are counted as `Unknown(<code>)`.
+fn tag_counts(headers: &[TagHeader]) -> BTreeMap<String, u32> {
+    let mut counts = BTreeMap::new();
+    for header in headers {
+        let name = match swf::TagCode::from_u16(header.code) {
+            Some(code) => format!("{:?}", code),
+            None => format!("Unknown({})", header.code),
+        };
+        *counts.entry(name).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Describes each known tag type among `headers`, keyed by the names `tag_counts` uses.
+fn tag_descriptions(headers: &[TagHeader]) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .filter_map(|header| swf::TagCode::from_u16(header.code))
+        .map(|code| (format!("{:?}", code), tag_description(code).to_string()))
+        .collect()
+}
+
+/// Returns a short human-readable account of what a `code` tag holds.
+fn tag_description(code: swf::TagCode) -> &'static str {
+    use swf::TagCode::*;
+
+    match code {
+        End => "end of the tag stream",
+        ShowFrame => "displays the current frame",
+        DefineShape => "vector shape",
+        PlaceObject => "places a character on the display list",
+        RemoveObject => "removes a character from the display list",
+        DefineBits => "JPEG image sharing the JpegTables encoding tables",
+        DefineButton => "button",
+        JpegTables => "JPEG encoding tables shared by DefineBits images",
+        SetBackgroundColor => "stage background color",
+        DefineFont => "font glyph outlines",
+        DefineText => "static text",
+        DoAction => "AVM1 actions run with the frame",
+        DefineFontInfo => "font name and character codes",
+        DefineSound => "event sound",
+        StartSound => "starts an event sound",
+        DefineButtonSound => "sounds played on button state changes",
+        SoundStreamHead => "streaming sound format",
+        SoundStreamBlock => "streaming sound data for one frame",
+        DefineBitsLossless => "lossless bitmap",
+        DefineBitsJpeg2 => "JPEG image with its own encoding tables",
+        DefineShape2 => "vector shape with more than 255 styles",
+        DefineButtonCxform => "button color transform",
+        Protect => "asks authoring tools not to import the file",
+        PlaceObject2 => "places or modifies a character on the display list",
+        RemoveObject2 => "removes a depth from the display list",
+        DefineShape3 => "vector shape with transparent colors",
+        DefineText2 => "static text with transparent colors",
+        DefineButton2 => "button with actions on state transitions",
+        DefineBitsJpeg3 => "JPEG image with an alpha channel",
+        DefineBitsLossless2 => "lossless bitmap with an alpha channel",
+        DefineEditText => "dynamic or input text field",
+        DefineSprite => "movie clip with its own timeline",
+        NameCharacter => "names a character for authoring tools",
+        ProductInfo => "authoring tool and build information",
+        FrameLabel => "names the current frame",
+        SoundStreamHead2 => "streaming sound format, any codec",
+        DefineMorphShape => "shape tween",
+        DefineFont2 => "font with metrics and kerning",
+        ExportAssets => "exports characters for other SWFs to import",
+        ImportAssets => "imports characters exported by another SWF",
+        EnableDebugger => "allows debugging, with an MD5 password",
+        DoInitAction => "AVM1 actions run once before a sprite is first used",
+        DefineVideoStream => "embedded video stream",
+        VideoFrame => "one frame of an embedded video",
+        DefineFontInfo2 => "font name, character codes and language",
+        DebugId => "id matching the file to its debug symbols",
+        EnableDebugger2 => "allows debugging, with a salted password",
+        ScriptLimits => "script recursion depth and timeout",
+        SetTabIndex => "tab order of a display list depth",
+        FileAttributes => "file-wide flags such as AVM2 use and network access",
+        PlaceObject3 => "places a character with filters, blend modes or a class name",
+        ImportAssets2 => "imports characters exported by another SWF",
+        DoAbc => "ActionScript 3 bytecode",
+        DefineFontAlignZones => "font alignment zones for advanced anti-aliasing",
+        CsmTextSettings => "advanced anti-aliasing settings for text",
+        DefineFont3 => "font with glyphs in twentieths of a pixel",
+        SymbolClass => "links characters to ActionScript 3 classes",
+        Metadata => "XMP metadata",
+        DefineScalingGrid => "9-slice scaling grid",
+        DoAbc2 => "named ActionScript 3 bytecode",
+        DefineShape4 => "vector shape with extended fills and line styles",
+        DefineMorphShape2 => "shape tween with extended line styles",
+        DefineSceneAndFrameLabelData => "scene and frame label names",
+        DefineBinaryData => "embedded binary data",
+        DefineFontName => "font name and copyright",
+        StartSound2 => "starts a sound by class name",
+        DefineBitsJpeg4 => "JPEG image with an alpha channel and deblocking",
+        DefineFont4 => "CFF font for the text layout framework",
+        EnableTelemetry => "allows telemetry, with an optional password",
+        PlaceObject4 => "places a character with AMF data attached",
+    }
+}
+
+/// Returns the SWF version that introduced `code`, per the SWF specification, or `None`
+/// for undocumented tags.
+fn tag_min_version(code: swf::TagCode) -> Option<u8> {
+    use swf::TagCode::*;
+
+    match code {
+        End | ShowFrame | DefineShape | PlaceObject | RemoveObject | DefineBits | DefineButton
+        | JpegTables | SetBackgroundColor | DefineFont | DefineText | DoAction | DefineFontInfo
+        | DefineSound | StartSound | SoundStreamHead | SoundStreamBlock | Metadata => Some(1),
+        DefineButtonSound | DefineBitsLossless | DefineBitsJpeg2 | DefineShape2
+        | DefineButtonCxform | Protect => Some(2),
+        PlaceObject2 | RemoveObject2 | DefineShape3 | DefineText2 | DefineButton2
+        | DefineBitsJpeg3 | DefineBitsLossless2 | DefineSprite | NameCharacter | FrameLabel
+        | SoundStreamHead2 | DefineMorphShape | DefineFont2 => Some(3),
+        DefineEditText => Some(4),
+        ExportAssets | ImportAssets | EnableDebugger => Some(5),
+        DoInitAction | DefineVideoStream | VideoFrame | DefineFontInfo2 | EnableDebugger2 => {
+            Some(6)
+        }
+        ScriptLimits | SetTabIndex => Some(7),
+        FileAttributes | PlaceObject3 | ImportAssets2 | DefineFontAlignZones | CsmTextSettings
+        | DefineFont3 | DefineScalingGrid | DefineShape4 | DefineMorphShape2 => Some(8),
+        DoAbc
+        | DoAbc2
+        | SymbolClass
+        | DefineSceneAndFrameLabelData
+        | DefineBinaryData
+        | DefineFontName
+        | StartSound2 => Some(9),
+        DefineBitsJpeg4 | DefineFont4 => Some(10),
+        ProductInfo | DebugId | EnableTelemetry | PlaceObject4 => None,
+    }
+}
+
+/// Checks each ImportAssets symbol against the ExportAssets of the sibling file it names.
+///
+/// Imports whose target file is not present next to `path` (e.g. remote URLs) can't be
+/// checked and are left alone; a present target that fails to parse breaks all of its imports.
+fn find_broken_imports(path: &Path, swf: &swf::Swf) -> Vec<String> {
+    let encoding = swf::SwfStr::encoding_for_version(swf.header.version());
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut broken = Vec::new();
+
+    for tag in &swf.tags {
+        if let swf::Tag::ImportAssets { url, imports } = tag {
+            let url = url.to_string_lossy(encoding);
+            // Only the file name matters when looking for a sibling; drop any query string
+            let target_name = url.split(['?', '#']).next().unwrap_or("");
+            let Some(target_name) = target_name.rsplit(['/', '\\']).next() else {
+                continue;
+            };
+            let target_path = dir.join(target_name);
+            if target_name.is_empty() || !target_path.is_file() {
+                continue;
+            }
+
+            let exports = read_exported_names(&target_path).unwrap_or_default();
+            for import in imports {
+                let name = import.name.to_string_lossy(encoding);
+                if !exports.contains(&name) {
+                    broken.push(format!("{}:{}", url, name));
+                }
+            }
+        }
+    }
+
+    broken
+}
+
+/// Walks `tags` and nested sprites, collecting the ids of defined characters and
+/// the ids referenced by placements, button records, exports and symbol classes.
+fn collect_character_ids(
+    tags: &[swf::Tag],
+    defined: &mut HashSet<swf::CharacterId>,
+    referenced: &mut HashSet<swf::CharacterId>,
+) {
+    for tag in tags {
+        if let Some(id) = defined_character_id(tag) {
+            defined.insert(id);
+        }
+        match tag {
+            swf::Tag::DefineSprite(sprite) => {
+                collect_character_ids(&sprite.tags, defined, referenced)
+            }
+            swf::Tag::PlaceObject(place) => match place.action {
+                swf::PlaceObjectAction::Place(id) | swf::PlaceObjectAction::Replace(id) => {
+                    referenced.insert(id);
+                }
+                swf::PlaceObjectAction::Modify => {}
+            },
+            swf::Tag::DefineButton(button) | swf::Tag::DefineButton2(button) => {
+                referenced.extend(button.records.iter().map(|record| record.id));
+            }
+            swf::Tag::ExportAssets(assets) => {
+                referenced.extend(assets.iter().map(|asset| asset.id));
+            }
+            swf::Tag::SymbolClass(links) => {
+                referenced.extend(links.iter().map(|link| link.id));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether a `StartSound`/`StartSound2` on the root timeline or in a sprite plays its
+/// sound more than once, as background music does; an envelope alone only shapes the
+/// volume of a one-shot effect.
+fn has_looping_sound(tags: &[swf::Tag]) -> bool {
+    tags.iter().any(|tag| match tag {
+        swf::Tag::StartSound(swf::StartSound { sound_info, .. })
+        | swf::Tag::StartSound2 { sound_info, .. } => {
+            sound_info.event != swf::SoundEvent::Stop && sound_info.num_loops > 1
+        }
+        swf::Tag::DefineSprite(sprite) => has_looping_sound(&sprite.tags),
+        _ => false,
+    })
+}
+
+/// Counts the `PlaceObject` tags that place or replace each character, on the root
+/// timeline and in sprites.
+fn count_placements(tags: &[swf::Tag], placements: &mut HashMap<swf::CharacterId, u32>) {
+    for tag in tags {
+        match tag {
+            swf::Tag::DefineSprite(sprite) => count_placements(&sprite.tags, placements),
+            swf::Tag::PlaceObject(place) => match place.action {
+                swf::PlaceObjectAction::Place(id) | swf::PlaceObjectAction::Replace(id) => {
+                    *placements.entry(id).or_default() += 1;
+                }
+                swf::PlaceObjectAction::Modify => {}
+            },
+            _ => {}
+        }
+    }
+}
+
+/// Returns the character id a definition tag introduces, if any.
+fn defined_character_id(tag: &swf::Tag) -> Option<swf::CharacterId> {
+    match tag {
+        swf::Tag::DefineBinaryData(data) => Some(data.id),
+        swf::Tag::DefineBits { id, .. } | swf::Tag::DefineBitsJpeg2 { id, .. } => Some(*id),
+        swf::Tag::DefineBitsJpeg3(jpeg) => Some(jpeg.id),
+        swf::Tag::DefineBitsLossless(bitmap) => Some(bitmap.id),
+        swf::Tag::DefineButton(button) | swf::Tag::DefineButton2(button) => Some(button.id),
+        swf::Tag::DefineEditText(edit_text) => Some(edit_text.id()),
+        swf::Tag::DefineMorphShape(morph) => Some(morph.id),
+        swf::Tag::DefineShape(shape) => Some(shape.id),
+        swf::Tag::DefineSound(sound) => Some(sound.id),
+        swf::Tag::DefineSprite(sprite) => Some(sprite.id),
+        swf::Tag::DefineText(text) | swf::Tag::DefineText2(text) => Some(text.id),
+        swf::Tag::DefineVideoStream(video) => Some(video.id),
+        _ => None,
+    }
+}
+
+/// A value tracked while scanning bytecode for statically known assignments
+#[derive(Clone, Debug, PartialEq)]
+enum ScriptValue {
+    Str(String),
+    Number(f64),
+    /// The result of reading the named variable or property
+    Name(String),
+    Unknown,
+}
+
+/// Collects `stage.<property> = <value>` assignments from the AVM1 and AVM2 bytecode
+/// in `swf`, in the order they appear. Only values pushed right before the assignment
+/// are resolved; anything computed at runtime is reported as `ScriptValue::Unknown`.
+fn stage_assignments(swf: &swf::Swf) -> Vec<(String, ScriptValue)> {
+    let mut assignments = Vec::new();
+    collect_stage_assignments(&swf.tags, swf.header.version(), &mut assignments);
+    assignments
+}
+
+fn collect_stage_assignments(
+    tags: &[swf::Tag],
+    version: u8,
+    assignments: &mut Vec<(String, ScriptValue)>,
+) {
+    for tag in tags {
+        match tag {
+            swf::Tag::DoAction(action_data) | swf::Tag::DoInitAction { action_data, .. } => {
+                scan_avm1_stage_assignments(action_data, version, &mut Vec::new(), assignments)
+            }
+            swf::Tag::DoAbc(data) => scan_abc_stage_assignments(data, assignments),
+            swf::Tag::DoAbc2(abc) => scan_abc_stage_assignments(abc.data, assignments),
+            swf::Tag::DefineSprite(sprite) => {
+                collect_stage_assignments(&sprite.tags, version, assignments)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Records an assignment if `object` is the result of reading `stage` (AS3) or the
+/// `Stage` global (AS2).
+fn record_stage_assignment(
+    object: Option<ScriptValue>,
+    property: Option<String>,
+    value: Option<ScriptValue>,
+    assignments: &mut Vec<(String, ScriptValue)>,
+) {
+    if let (Some(ScriptValue::Name(object)), Some(property)) = (object, property)
+        && object.eq_ignore_ascii_case("stage")
+    {
+        assignments.push((property, value.unwrap_or(ScriptValue::Unknown)));
+    }
+}
+
+fn scan_avm1_stage_assignments(
+    action_data: &[u8],
+    version: u8,
+    constant_pool: &mut Vec<String>,
+    assignments: &mut Vec<(String, ScriptValue)>,
+) {
+    use swf::avm1::types::{Action, Value};
+
+    let encoding = swf::SwfStr::encoding_for_version(version);
+    let mut reader = swf::avm1::read::Reader::new(action_data, version);
+    let mut stack = Vec::new();
+    while let Ok(action) = reader.read_action() {
+        match action {
+            Action::End => break,
+            Action::ConstantPool(pool) => {
+                *constant_pool = pool
+                    .strings
+                    .iter()
+                    .map(|s| s.to_string_lossy(encoding))
+                    .collect();
+                stack.clear();
+            }
+            Action::Push(push) => stack.extend(push.values.iter().map(|value| {
+                match value {
+                    Value::Str(s) => ScriptValue::Str(s.to_string_lossy(encoding)),
+                    Value::Int(n) => ScriptValue::Number(*n as f64),
+                    Value::Float(n) => ScriptValue::Number(*n as f64),
+                    Value::Double(n) => ScriptValue::Number(*n),
+                    Value::ConstantPool(i) => constant_pool
+                        .get(*i as usize)
+                        .map_or(ScriptValue::Unknown, |s| ScriptValue::Str(s.clone())),
+                    _ => ScriptValue::Unknown,
+                }
+            })),
+            Action::GetVariable => {
+                let name = stack.pop();
+                stack.push(match name {
+                    Some(ScriptValue::Str(name)) => ScriptValue::Name(name),
+                    _ => ScriptValue::Unknown,
+                });
+            }
+            Action::GetMember => {
+                let name = stack.pop();
+                stack.pop();
+                stack.push(match name {
+                    Some(ScriptValue::Str(name)) => ScriptValue::Name(name),
+                    _ => ScriptValue::Unknown,
+                });
+            }
+            Action::SetMember => {
+                let value = stack.pop();
+                let property = match stack.pop() {
+                    Some(ScriptValue::Str(name)) => Some(name),
+                    _ => None,
+                };
+                record_stage_assignment(stack.pop(), property, value, assignments);
+            }
+            Action::DefineFunction(function) => {
+                scan_avm1_stage_assignments(function.actions, version, constant_pool, assignments)
+            }
+            Action::DefineFunction2(function) => {
+                scan_avm1_stage_assignments(function.actions, version, constant_pool, assignments)
+            }
+            // Any other action has a stack effect we don't model
+            _ => stack.clear(),
+        }
+    }
+}
+
+/// Heuristic for loader wrappers. A SWF counts as a preloader stub when all of these hold:
+///
+/// - it has at most 3 frames,
+/// - its stage is no larger than Flash's default 550x400,
+/// - it has exactly one `DoAction` (including those in sprites), and
+/// - that action loads an external `.swf` through `getURL`/`loadMovie` with a literal URL.
+///
+/// AS3 preloaders, which load through `flash.display.Loader`, are not detected.
+fn is_preloader(swf: &swf::Swf, metadata: &Metadata) -> bool {
+    let (width, height) = metadata.stage_size;
+    if metadata.no_of_frames > 3 || width > 550 || height > 400 {
+        return false;
+    }
+    let mut actions = Vec::new();
+    collect_do_actions(&swf.tags, &mut actions);
+    let [action_data] = actions[..] else {
+        return false;
+    };
+    let mut urls = Vec::new();
+    collect_avm1_urls(action_data, swf.header.version(), &mut urls);
+    urls.iter().any(|url| {
+        let path = url.split(['?', '#']).next().unwrap_or_default();
+        path.to_ascii_lowercase().ends_with(".swf")
+    })
+}
+
+/// Heuristic for custom mouse pointers. A SWF counts as drawing its own cursor when its
+/// scripts either register a native cursor with `Mouse.registerCursor` (AS3), or hide the
+/// system pointer with `Mouse.hide()` and move a clip along with it, through `startDrag`
+/// or by reading `_xmouse` (AS2) or `mouseX` (AS3).
+///
+/// Only the names the scripts mention are checked, not whether the calls are reachable.
+fn has_custom_cursor(swf: &swf::Swf) -> bool {
+    let mut names = HashSet::new();
+    collect_script_names(&swf.tags, swf.header.version(), &mut names);
+    let has = |name: &str| names.contains(name);
+    let follows_pointer = has("startDrag") || has("_xmouse") || has("mouseX");
+    has("Mouse") && (has("registerCursor") || (has("hide") && follows_pointer))
+}
+
+/// Collects the string literals AVM1 actions push and the strings in ABC constant pools,
+/// on the root timeline and in sprites. The AVM1 `startDrag` action is recorded as the
+/// name `startDrag`, the method AS3 calls instead.
+fn collect_script_names(tags: &[swf::Tag], version: u8, names: &mut HashSet<String>) {
+    for tag in tags {
+        match tag {
+            swf::Tag::DoAction(action_data) | swf::Tag::DoInitAction { action_data, .. } => {
+                collect_avm1_names(action_data, version, names)
+            }
+            swf::Tag::DoAbc(data) => collect_abc_names(data, names),
+            swf::Tag::DoAbc2(abc) => collect_abc_names(abc.data, names),
+            swf::Tag::DefineSprite(sprite) => collect_script_names(&sprite.tags, version, names),
+            _ => {}
+        }
+    }
+}
+
+fn collect_avm1_names(action_data: &[u8], version: u8, names: &mut HashSet<String>) {
+    use swf::avm1::types::{Action, Value};
+
+    let encoding = swf::SwfStr::encoding_for_version(version);
+    let mut reader = swf::avm1::read::Reader::new(action_data, version);
+    while let Ok(action) = reader.read_action() {
+        match action {
+            Action::End => break,
+            Action::ConstantPool(pool) => {
+                names.extend(pool.strings.iter().map(|s| s.to_string_lossy(encoding)))
+            }
+            Action::Push(push) => {
+                for value in &push.values {
+                    if let Value::Str(s) = value {
+                        names.insert(s.to_string_lossy(encoding));
+                    }
+                }
+            }
+            Action::StartDrag => {
+                names.insert("startDrag".to_string());
+            }
+            Action::DefineFunction(function) => {
+                collect_avm1_names(function.actions, version, names)
+            }
+            Action::DefineFunction2(function) => {
+                collect_avm1_names(function.actions, version, names)
+            }
+            _ => {}
+        }
+    }
+}
+
+fn collect_abc_names(data: &[u8], names: &mut HashSet<String>) {
+    if let Ok(abc) = swf::avm2::read::Reader::new(data).read() {
+        names.extend(
+            abc.constant_pool
+                .strings
+                .iter()
+                .map(|s| String::from_utf8_lossy(s).into_owned()),
+        );
+    }
+}
+
+/// Returns 3 when the file carries ABC bytecode, 1 when it only carries AVM1 actions, and
+/// `None` when it has no scripts at all.
+fn actionscript_version(tags: &[swf::Tag]) -> Option<u8> {
+    // ABC bytecode is only honoured on the root timeline
+    if tags
+        .iter()
+        .any(|tag| matches!(tag, swf::Tag::DoAbc(_) | swf::Tag::DoAbc2(_)))
+    {
+        Some(3)
+    } else if has_avm1_code(tags) {
+        Some(1)
+    } else {
+        None
+    }
+}
+
+/// Whether `tags`, or the tags of any sprite they define, carry AVM1 bytecode.
+fn has_avm1_code(tags: &[swf::Tag]) -> bool {
+    tags.iter().any(|tag| match tag {
+        swf::Tag::DoAction(_) | swf::Tag::DoInitAction { .. } => true,
+        swf::Tag::DefineSprite(sprite) => has_avm1_code(&sprite.tags),
+        _ => false,
+    })
+}
+
+fn collect_do_actions<'a>(tags: &'a [swf::Tag<'a>], actions: &mut Vec<&'a [u8]>) {
+    for tag in tags {
+        match tag {
+            swf::Tag::DoAction(action_data) => actions.push(action_data),
+            swf::Tag::DefineSprite(sprite) => collect_do_actions(&sprite.tags, actions),
+            _ => {}
+        }
+    }
+}
+
+/// Collects the URLs AVM1 `getURL` calls in `action_data` open, when they are string
+/// literals.
+fn collect_avm1_urls(action_data: &[u8], version: u8, urls: &mut Vec<String>) {
+    use swf::avm1::types::{Action, Value};
+
+    let encoding = swf::SwfStr::encoding_for_version(version);
+    let mut reader = swf::avm1::read::Reader::new(action_data, version);
+    let mut constant_pool = Vec::new();
+    let mut stack = Vec::new();
+    while let Ok(action) = reader.read_action() {
+        match action {
+            Action::End => break,
+            Action::ConstantPool(pool) => {
+                constant_pool = pool
+                    .strings
+                    .iter()
+                    .map(|s| s.to_string_lossy(encoding))
+                    .collect();
+                stack.clear();
+            }
+            Action::Push(push) => stack.extend(push.values.iter().map(|value| match value {
+                Value::Str(s) => Some(s.to_string_lossy(encoding)),
+                Value::ConstantPool(i) => constant_pool.get(*i as usize).cloned(),
+                _ => None,
+            })),
+            Action::GetUrl(get_url) => urls.push(get_url.url.to_string_lossy(encoding)),
+            // getURL with computed arguments pops the target, then the URL
+            Action::GetUrl2(_) => {
+                stack.pop();
+                if let Some(Some(url)) = stack.pop() {
+                    urls.push(url);
+                }
+            }
+            _ => stack.clear(),
+        }
+    }
+}
+
+/// Looks for the runtime shared library list Flex compiles into its `SystemManager`.
+///
+/// An ABC constant pool holding the `rsls` or `cdRsls` keys marks a SWF that loads
+/// RSLs; the library names are the pool strings naming `.swz` or `.swf` files. Returns
+/// `None` if no RSL list is present.
+fn find_rsl_libraries(swf: &swf::Swf) -> Option<Vec<String>> {
+    let mut uses_rsl = false;
+    let mut libraries = BTreeSet::new();
+    for tag in &swf.tags {
+        let data = match tag {
+            swf::Tag::DoAbc(data) => data,
+            swf::Tag::DoAbc2(abc) => &abc.data,
+            _ => continue,
+        };
+        let Ok(abc) = swf::avm2::read::Reader::new(data).read() else {
+            continue;
+        };
+        let strings = &abc.constant_pool.strings;
+        if !strings
+            .iter()
+            .any(|s| s.as_slice() == b"rsls" || s.as_slice() == b"cdRsls")
+        {
+            continue;
+        }
+        uses_rsl = true;
+        for s in strings {
+            let s = String::from_utf8_lossy(s);
+            let lower = s.to_ascii_lowercase();
+            if lower.ends_with(".swz") || lower.ends_with(".swf") {
+                libraries.insert(s.into_owned());
+            }
+        }
+    }
+    uses_rsl.then(|| libraries.into_iter().collect())
+}
+
+fn scan_abc_stage_assignments(data: &[u8], assignments: &mut Vec<(String, ScriptValue)>) {
+    use swf::avm2::types::{Multiname, Op};
+
+    let Ok(abc) = swf::avm2::read::Reader::new(data).read() else {
+        return;
+    };
+    let pool = &abc.constant_pool;
+    // Constant pool indices are one-based, index zero means "no value"
+    let string = |index: u32| {
+        let i = index.checked_sub(1)? as usize;
+        Some(String::from_utf8_lossy(pool.strings.get(i)?).into_owned())
+    };
+    let multiname = |index: u32| match pool.multinames.get(index.checked_sub(1)? as usize)? {
+        Multiname::QName { name, .. }
+        | Multiname::QNameA { name, .. }
+        | Multiname::RTQName { name }
+        | Multiname::RTQNameA { name }
+        | Multiname::Multiname { name, .. }
+        | Multiname::MultinameA { name, .. } => string(name.0),
+        _ => None,
+    };
+
+    for body in &abc.method_bodies {
+        let mut reader = swf::avm2::read::Reader::new(&body.code);
+        let mut stack = Vec::new();
+        while let Ok(op) = reader.read_op() {
+            match op {
+                Op::PushString { value } => {
+                    stack.push(string(value.0).map_or(ScriptValue::Unknown, ScriptValue::Str))
+                }
+                Op::PushByte { value } => stack.push(ScriptValue::Number(value as i8 as f64)),
+                Op::PushShort { value } => stack.push(ScriptValue::Number(value as f64)),
+                Op::PushInt { value } => stack.push(number(&pool.ints, value.0)),
+                Op::PushUint { value } => stack.push(number(&pool.uints, value.0)),
+                Op::PushDouble { value } => stack.push(number(&pool.doubles, value.0)),
+                Op::GetLocal { .. } | Op::FindProperty { .. } | Op::FindPropStrict { .. } => {
+                    stack.push(ScriptValue::Unknown)
+                }
+                Op::GetLex { index } => {
+                    stack.push(multiname(index.0).map_or(ScriptValue::Unknown, ScriptValue::Name))
+                }
+                Op::GetProperty { index } => match multiname(index.0) {
+                    Some(name) => {
+                        let value = match stack.pop() {
+                            Some(ScriptValue::Name(class)) => stage_constant(&class, &name)
+                                .map(|value| ScriptValue::Str(value.to_string())),
+                            _ => None,
+                        };
+                        stack.push(value.unwrap_or(ScriptValue::Name(name)));
+                    }
+                    // Runtime names take extra operands from the stack
+                    None => stack.clear(),
+                },
+                Op::SetProperty { index } | Op::InitProperty { index } => {
+                    match multiname(index.0) {
+                        Some(property) => {
+                            let value = stack.pop();
+                            record_stage_assignment(
+                                stack.pop(),
+                                Some(property),
+                                value,
+                                assignments,
+                            );
+                        }
+                        None => stack.clear(),
+                    }
+                }
+                // Any other op has a stack effect we don't model
+                _ => stack.clear(),
+            }
+        }
+    }
+}
+
+/// Looks up a one-based ABC constant pool number.
+fn number<T: Copy + Into<f64>>(pool: &[T], index: u32) -> ScriptValue {
+    index
+        .checked_sub(1)
+        .and_then(|i| pool.get(i as usize))
+        .map_or(ScriptValue::Unknown, |&n| ScriptValue::Number(n.into()))
+}
+
+/// Resolves the `StageScaleMode` and `StageAlign` constants to their string values.
+fn stage_constant(class: &str, name: &str) -> Option<&'static str> {
+    match (class, name) {
+        ("StageScaleMode", "EXACT_FIT") => Some("exactFit"),
+        ("StageScaleMode", "NO_BORDER") => Some("noBorder"),
+        ("StageScaleMode", "NO_SCALE") => Some("noScale"),
+        ("StageScaleMode", "SHOW_ALL") => Some("showAll"),
+        ("StageAlign", "TOP") => Some("T"),
+        ("StageAlign", "BOTTOM") => Some("B"),
+        ("StageAlign", "LEFT") => Some("L"),
+        ("StageAlign", "RIGHT") => Some("R"),
+        ("StageAlign", "TOP_LEFT") => Some("TL"),
+        ("StageAlign", "TOP_RIGHT") => Some("TR"),
+        ("StageAlign", "BOTTOM_LEFT") => Some("BL"),
+        ("StageAlign", "BOTTOM_RIGHT") => Some("BR"),
+        _ => None,
+    }
+}
+
+/// The ActionScript handler names of the events a clip action can respond to
+const CLIP_EVENT_NAMES: [(swf::ClipEventFlag, &str); 19] = [
+    (swf::ClipEventFlag::LOAD, "onLoad"),
+    (swf::ClipEventFlag::ENTER_FRAME, "onEnterFrame"),
+    (swf::ClipEventFlag::UNLOAD, "onUnload"),
+    (swf::ClipEventFlag::MOUSE_MOVE, "onMouseMove"),
+    (swf::ClipEventFlag::MOUSE_DOWN, "onMouseDown"),
+    (swf::ClipEventFlag::MOUSE_UP, "onMouseUp"),
+    (swf::ClipEventFlag::KEY_DOWN, "onKeyDown"),
+    (swf::ClipEventFlag::KEY_UP, "onKeyUp"),
+    (swf::ClipEventFlag::DATA, "onData"),
+    (swf::ClipEventFlag::INITIALIZE, "onInitialize"),
+    (swf::ClipEventFlag::PRESS, "onPress"),
+    (swf::ClipEventFlag::RELEASE, "onRelease"),
+    (swf::ClipEventFlag::RELEASE_OUTSIDE, "onReleaseOutside"),
+    (swf::ClipEventFlag::ROLL_OVER, "onRollOver"),
+    (swf::ClipEventFlag::ROLL_OUT, "onRollOut"),
+    (swf::ClipEventFlag::DRAG_OVER, "onDragOver"),
+    (swf::ClipEventFlag::DRAG_OUT, "onDragOut"),
+    (swf::ClipEventFlag::KEY_PRESS, "onKeyPress"),
+    (swf::ClipEventFlag::CONSTRUCT, "onConstruct"),
+];
+
+/// Counts the clip event handlers that AVM1 `PlaceObject2`/`PlaceObject3` tags attach,
+/// on the root timeline and in sprites, and collects the events they handle.
+fn collect_clip_events(tags: &[swf::Tag], count: &mut u32, events: &mut BTreeSet<&'static str>) {
+    for tag in tags {
+        match tag {
+            swf::Tag::PlaceObject(place) => {
+                for clip_action in place.clip_actions.iter().flatten() {
+                    *count += 1;
+                    events.extend(
+                        CLIP_EVENT_NAMES
+                            .iter()
+                            .filter(|(flag, _)| clip_action.events.contains(*flag))
+                            .map(|(_, name)| *name),
+                    );
+                }
+            }
+            swf::Tag::DefineSprite(sprite) => collect_clip_events(&sprite.tags, count, events),
+            _ => {}
+        }
+    }
+}
+
+/// Collects the blend modes `PlaceObject3` tags set, on the root timeline and in sprites.
+fn collect_blend_modes(tags: &[swf::Tag], blend_modes: &mut BTreeSet<&'static str>) {
+    use swf::BlendMode::*;
+
+    for tag in tags {
+        match tag {
+            swf::Tag::PlaceObject(place) => {
+                if let Some(mode) = place.blend_mode {
+                    blend_modes.insert(match mode {
+                        Normal => "normal",
+                        Layer => "layer",
+                        Multiply => "multiply",
+                        Screen => "screen",
+                        Lighten => "lighten",
+                        Darken => "darken",
+                        Difference => "difference",
+                        Add => "add",
+                        Subtract => "subtract",
+                        Invert => "invert",
+                        Alpha => "alpha",
+                        Erase => "erase",
+                        Overlay => "overlay",
+                        HardLight => "hardlight",
+                    });
+                }
+            }
+            swf::Tag::DefineSprite(sprite) => collect_blend_modes(&sprite.tags, blend_modes),
+            _ => {}
+        }
+    }
+}
+
+/// Counts the `PlaceObject3` tags that turn on cacheAsBitmap, on the root timeline and
+/// in sprites.
+fn count_cached_placements(tags: &[swf::Tag]) -> u32 {
+    tags.iter()
+        .map(|tag| match tag {
+            swf::Tag::PlaceObject(place) => (place.is_bitmap_cached == Some(true)) as u32,
+            swf::Tag::DefineSprite(sprite) => count_cached_placements(&sprite.tags),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Sums the glyphs of the `DefineFont`, `DefineFont2` and `DefineFont3` tags. Fonts are
+/// always defined at the top level. `DefineFont4` embeds a CFF program instead of glyph
+/// records, so it adds nothing.
+fn count_glyphs(tags: &[swf::Tag]) -> u32 {
+    tags.iter()
+        .map(|tag| match tag {
+            swf::Tag::DefineFont(font) => font.glyphs.len() as u32,
+            swf::Tag::DefineFont2(font) => font.glyphs.len() as u32,
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Collects the defined video stream ids and the ids of the streams that have frames.
+/// Frames may sit on the root timeline or inside the sprite that places the stream.
+fn collect_video_streams(
+    tags: &[swf::Tag],
+    streams: &mut Vec<swf::CharacterId>,
+    with_frames: &mut HashSet<swf::CharacterId>,
+) {
+    for tag in tags {
+        match tag {
+            swf::Tag::DefineVideoStream(stream) => streams.push(stream.id),
+            swf::Tag::VideoFrame(frame) => {
+                with_frames.insert(frame.stream_id);
+            }
+            swf::Tag::DefineSprite(sprite) => {
+                collect_video_streams(&sprite.tags, streams, with_frames)
+            }
+            _ => {}
+        }
+    }
+}
+
+fn collect_morph_shapes(tags: &[swf::Tag], morph_shapes: &mut HashSet<swf::CharacterId>) {
+    for tag in tags {
+        match tag {
+            swf::Tag::DefineMorphShape(morph) => {
+                morph_shapes.insert(morph.id);
+            }
+            swf::Tag::DefineSprite(sprite) => collect_morph_shapes(&sprite.tags, morph_shapes),
+            _ => {}
+        }
+    }
+}
+
+/// Counts the places in `tags` and nested sprites that set a ratio on a morph shape,
+/// i.e. the frames of a shape tween.
+fn count_morph_tweens(tags: &[swf::Tag], morph_shapes: &HashSet<swf::CharacterId>) -> u32 {
+    let mut characters = HashMap::new();
+    let mut count = 0;
+    for tag in tags {
+        match tag {
+            swf::Tag::PlaceObject(place) => {
+                let character = match place.action {
+                    swf::PlaceObjectAction::Place(id) | swf::PlaceObjectAction::Replace(id) => {
+                        characters.insert(place.depth, id);
+                        Some(id)
+                    }
+                    swf::PlaceObjectAction::Modify => characters.get(&place.depth).copied(),
+                };
+                if place.ratio.is_some() && character.is_some_and(|id| morph_shapes.contains(&id)) {
+                    count += 1;
+                }
+            }
+            swf::Tag::RemoveObject(remove) => {
+                characters.remove(&remove.depth);
+            }
+            swf::Tag::DefineSprite(sprite) => {
+                count += count_morph_tweens(&sprite.tags, morph_shapes)
+            }
+            _ => {}
+        }
+    }
+    count
+}
+
+/// Plays back the root display list and checks whether any placed shape's transformed
+/// bounding box reaches outside the stage rectangle.
+fn has_offstage_content(swf: &swf::Swf) -> bool {
+    let shape_bounds: HashMap<u16, &swf::Rectangle<swf::Twips>> = swf
+        .tags
+        .iter()
+        .filter_map(|tag| match tag {
+            swf::Tag::DefineShape(shape) => Some((shape.id, &shape.shape_bounds)),
+            _ => None,
+        })
+        .collect();
+    let stage = swf.header.stage_size();
+    let mut display_list: HashMap<u16, (u16, swf::Matrix)> = HashMap::new();
+
+    for tag in &swf.tags {
+        match tag {
+            swf::Tag::PlaceObject(place) => {
+                match place.action {
+                    swf::PlaceObjectAction::Place(id) => {
+                        display_list.insert(place.depth, (id, place.matrix.unwrap_or_default()));
+                    }
+                    swf::PlaceObjectAction::Replace(id) => {
+                        // Replacing a character keeps the existing transform unless a new one is given
+                        let previous = display_list.get(&place.depth).map(|(_, matrix)| *matrix);
+                        let matrix = place.matrix.or(previous).unwrap_or_default();
+                        display_list.insert(place.depth, (id, matrix));
+                    }
+                    swf::PlaceObjectAction::Modify => {
+                        if let (Some(matrix), Some(entry)) =
+                            (place.matrix, display_list.get_mut(&place.depth))
+                        {
+                            entry.1 = matrix;
+                        }
+                    }
+                }
+
+                if let Some((id, matrix)) = display_list.get(&place.depth)
+                    && let Some(bounds) = shape_bounds.get(id)
+                {
+                    let placed = transform_bounds(matrix, bounds);
+                    if placed.x_min < stage.x_min
+                        || placed.y_min < stage.y_min
+                        || placed.x_max > stage.x_max
+                        || placed.y_max > stage.y_max
+                    {
+                        return true;
+                    }
+                }
+            }
+            swf::Tag::RemoveObject(remove) => {
+                display_list.remove(&remove.depth);
+            }
+            _ => {}
+        }
+    }
+
+    false
+}
+
+fn transform_bounds(
+    matrix: &swf::Matrix,
+    bounds: &swf::Rectangle<swf::Twips>,
+) -> swf::Rectangle<swf::Twips> {
+    let corners = [
+        swf::Point::new(bounds.x_min, bounds.y_min),
+        swf::Point::new(bounds.x_max, bounds.y_min),
+        swf::Point::new(bounds.x_min, bounds.y_max),
+        swf::Point::new(bounds.x_max, bounds.y_max),
+    ];
+    corners
+        .into_iter()
+        .fold(swf::Rectangle::INVALID, |rect, corner| {
+            rect.encompass(*matrix * corner)
+        })
+}
+
+/// Returns the pixel dimensions of a bitmap-defining tag.
+///
+/// Lossless bitmaps carry their size directly; JPEG-family tags are sniffed from the
+/// embedded image data, which Flash allows to be JPEG, PNG or GIF.
+fn bitmap_dimensions(tag: &swf::Tag) -> Option<(u32, u32)> {
+    match tag {
+        swf::Tag::DefineBitsLossless(bitmap) => Some((bitmap.width.into(), bitmap.height.into())),
+        _ => image_dimensions(jpeg_tag_data(tag)?),
+    }
+}
+
+/// Returns the JPEG-family tag's image payload, which Flash allows to be PNG or GIF data.
+fn jpeg_tag_data<'a>(tag: &swf::Tag<'a>) -> Option<&'a [u8]> {
+    match tag {
+        swf::Tag::DefineBits { jpeg_data, .. } | swf::Tag::DefineBitsJpeg2 { jpeg_data, .. } => {
+            Some(jpeg_data)
+        }
+        swf::Tag::DefineBitsJpeg3(jpeg) => Some(jpeg.data),
+        _ => None,
+    }
+}
+
+/// Sniffs an image payload's magic bytes; anything that isn't PNG or GIF is taken as JPEG.
+fn image_format(data: &[u8]) -> &'static str {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        "png"
+    } else if data.starts_with(b"GIF8") {
+        "gif"
+    } else {
+        "jpeg"
+    }
+}
+
+fn image_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    if image_format(data) == "png" {
+        // IHDR is always the first chunk: width and height follow the chunk header
+        let width = u32::from_be_bytes(data.get(16..20)?.try_into().ok()?);
+        let height = u32::from_be_bytes(data.get(20..24)?.try_into().ok()?);
+        Some((width, height))
+    } else if image_format(data) == "gif" {
+        let width = u16::from_le_bytes(data.get(6..8)?.try_into().ok()?);
+        let height = u16::from_le_bytes(data.get(8..10)?.try_into().ok()?);
+        Some((width.into(), height.into()))
+    } else {
+        jpeg_dimensions(data)
+    }
+}
+
+/// Walks JPEG marker segments until a start-of-frame marker carrying the image size.
+fn jpeg_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    let mut pos = 0;
+    while pos + 1 < data.len() {
+        if data[pos] != 0xFF {
+            pos += 1;
+            continue;
+        }
+        let marker = data[pos + 1];
+        match marker {
+            // Fill byte: the real marker follows
+            0xFF => pos += 1,
+            // SOI/EOI (older SWFs often start with a bogus EOI+SOI pair) and restart
+            // markers stand alone without a length
+            0xD8 | 0xD9 | 0x01 | 0xD0..=0xD7 => pos += 2,
+            // SOF0-SOF15, except DHT (C4), JPG (C8) and DAC (CC) which share the range
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                let height = u16::from_be_bytes(data.get(pos + 5..pos + 7)?.try_into().ok()?);
+                let width = u16::from_be_bytes(data.get(pos + 7..pos + 9)?.try_into().ok()?);
+                return Some((width.into(), height.into()));
+            }
+            _ => {
+                let length = u16::from_be_bytes(data.get(pos + 2..pos + 4)?.try_into().ok()?);
+                pos += 2 + length as usize;
+            }
+        }
+    }
+    None
+}
+
+fn read_exported_names(path: &Path) -> Option<HashSet<String>> {
+    let data = std::fs::read(path).ok()?;
+    let swf_buf = swf::decompress_swf(&data[..]).ok()?;
+    let swf = swf::parse_swf(&swf_buf).ok()?;
+    let encoding = swf::SwfStr::encoding_for_version(swf.header.version());
+
+    let mut names = HashSet::new();
+    for tag in &swf.tags {
+        if let swf::Tag::ExportAssets(exports) = tag {
+            names.extend(exports.iter().map(|e| e.name.to_string_lossy(encoding)));
+        }
+    }
+    Some(names)
+}
+
+/// Names the compression the file was stored with: `none` (FWS), `zlib` (CWS) or `lzma` (ZWS)
+fn compression_name(compression: swf::Compression) -> &'static str {
+    match compression {
+        swf::Compression::None => "none",
+        swf::Compression::Zlib => "zlib",
+        swf::Compression::Lzma => "lzma",
+    }
+}
+
+/// Describes a frame rate that is likely an authoring mistake: a rate of 0, which
+/// players treat as "as fast as possible", or one above `max_fps`.
+fn frame_rate_warning(frame_rate: f32, max_fps: f32) -> Option<String> {
+    if frame_rate == 0.0 {
+        Some("frame rate is 0".to_string())
+    } else if frame_rate > max_fps {
+        Some(format!(
+            "frame rate {} exceeds the sane maximum of {}",
+            frame_rate, max_fps
+        ))
+    } else {
+        None
+    }
+}
+
+/// Snaps `frame_rate` to the nearest common rate within `FRAME_RATE_TOLERANCE`, so that
+/// e.g. NTSC's 29.97 catalogs as 30; any other rate is returned unchanged.
+fn normalize_frame_rate(frame_rate: f32) -> f32 {
+    COMMON_FRAME_RATES
+        .into_iter()
+        .find(|common| (frame_rate - common).abs() <= FRAME_RATE_TOLERANCE)
+        .unwrap_or(frame_rate)
+}
+
+fn build_metadata(file_name: &str, swf: &swf::Swf, options: &ExtractOptions) -> Metadata {
+    let stage_size = swf.header.stage_size();
+    let stage_width_twips = stage_size.x_max - stage_size.x_min;
+    let stage_height_twips = stage_size.y_max - stage_size.y_min;
+    let stage_width = stage_width_twips.to_pixels();
+    let stage_height = stage_height_twips.to_pixels();
+
+    let xmp_packet = swf.tags.iter().find_map(|tag| match tag {
+        swf::Tag::Metadata(xmp) => Some(*xmp),
+        _ => None,
+    });
+    // The HasMetadata bit in FileAttributes should match whether a Metadata tag exists
+    let has_metadata_tag = xmp_packet.is_some();
+
+    let mut metadata = Metadata {
+        file_name: file_name.to_string(),
+        stage_size: (stage_width as u32, stage_height as u32),
+        stage_size_precise: (stage_width as f32, stage_height as f32),
+        stage_size_twips: (stage_width_twips.get(), stage_height_twips.get()),
+        no_of_frames: swf.header.num_frames() as u32,
+        frame_rate: swf.header.frame_rate().to_f32(),
+        metadata_flag_consistent: swf.header.has_metadata() == has_metadata_tag,
+        swf_version: swf.header.version(),
+        compression: compression_name(swf.header.compression()).to_string(),
+        // The header only looks at the first few tags, which misses Flex-ordered files
+        background_color: swf
+            .tags
+            .iter()
+            .find_map(|tag| match tag {
+                swf::Tag::SetBackgroundColor(color) => Some(*color),
+                _ => None,
+            })
+            .map(|color| format!("#{:02X}{:02X}{:02X}", color.r, color.g, color.b)),
+        actionscript_version: actionscript_version(&swf.tags),
+        xmp_metadata: xmp_packet.map(|xmp| {
+            xmp.to_string_lossy(swf::SwfStr::encoding_for_version(swf.header.version()))
+        }),
+        // Players only honour FileAttributes as the first tag and ignore it anywhere else
+        file_attributes_first: swf
+            .tags
+            .iter()
+            .position(|tag| matches!(tag, swf::Tag::FileAttributes(_)))
+            .map(|position| position == 0),
+        // ABC bytecode is only honoured on the root timeline
+        mixed_avm: swf
+            .tags
+            .iter()
+            .any(|tag| matches!(tag, swf::Tag::DoAbc(_) | swf::Tag::DoAbc2(_)))
+            && has_avm1_code(&swf.tags),
+        frame_rate_warning: frame_rate_warning(
+            swf.header.frame_rate().to_f32(),
+            options.max_sane_fps.unwrap_or(DEFAULT_MAX_SANE_FPS),
+        ),
+        normalized_frame_rate: options
+            .normalize_frame_rate
+            .then(|| normalize_frame_rate(swf.header.frame_rate().to_f32())),
+        ..Default::default()
+    };
+
+    if options.displaylist {
+        // Depths are per timeline, so only the root display list is considered
+        let depths: BTreeSet<u16> = swf
+            .tags
+            .iter()
+            .filter_map(|tag| match tag {
+                swf::Tag::PlaceObject(place) => Some(place.depth),
+                _ => None,
+            })
+            .collect();
+        metadata.max_depth_used = Some(depths.last().copied().unwrap_or(0));
+        metadata.distinct_depths = Some(depths.len() as u32);
+
+        let mut blend_modes = BTreeSet::new();
+        collect_blend_modes(&swf.tags, &mut blend_modes);
+        metadata.blend_modes = Some(blend_modes.into_iter().map(str::to_string).collect());
+        metadata.cache_as_bitmap_count = Some(count_cached_placements(&swf.tags));
+    }
+
+    if options.fonts {
+        let encoding = swf::SwfStr::encoding_for_version(swf.header.version());
+        // DefineFont4 without font data only references a device font, so it embeds nothing
+        let cff_font_names: Vec<String> = swf
+            .tags
+            .iter()
+            .filter_map(|tag| match tag {
+                swf::Tag::DefineFont4(font) if font.data.is_some() => {
+                    Some(font.name.to_string_lossy(encoding))
+                }
+                _ => None,
+            })
+            .collect();
+        metadata.has_cff_fonts = Some(!cff_font_names.is_empty());
+        metadata.cff_font_names = Some(cff_font_names);
+        // DefineFontName attaches the display name and copyright notice to a font id
+        let font_copyrights = swf
+            .tags
+            .iter()
+            .filter_map(|tag| match tag {
+                swf::Tag::DefineFontName { copyright_info, .. } => {
+                    Some(copyright_info.to_string_lossy(encoding))
+                }
+                _ => None,
+            })
+            .collect();
+        metadata.font_copyrights = Some(font_copyrights);
+        metadata.total_glyph_count = Some(count_glyphs(&swf.tags));
+    }
+
+    if options.bitmaps {
+        // Decoded bitmaps are held as 32-bit RGBA, so each pixel costs four bytes
+        let estimated_bytes: u64 = swf
+            .tags
+            .iter()
+            .filter_map(bitmap_dimensions)
+            .map(|(width, height)| width as u64 * height as u64 * 4)
+            .sum();
+        metadata.estimated_bitmap_memory_bytes = Some(estimated_bytes);
+
+        let mut formats = BTreeMap::new();
+        for data in swf.tags.iter().filter_map(jpeg_tag_data) {
+            *formats.entry(image_format(data).to_string()).or_insert(0) += 1;
+        }
+        metadata.bitmap_formats = Some(formats);
+    }
+
+    if options.dead_code {
+        // A sprite without a single ShowFrame never displays anything
+        let empty_sprites = swf
+            .tags
+            .iter()
+            .filter(|tag| match tag {
+                swf::Tag::DefineSprite(sprite) => {
+                    !sprite.tags.iter().any(|t| matches!(t, swf::Tag::ShowFrame))
+                }
+                _ => false,
+            })
+            .count();
+        metadata.empty_sprite_count = Some(empty_sprites as u32);
+
+        let mut defined = HashSet::new();
+        let mut referenced = HashSet::new();
+        collect_character_ids(&swf.tags, &mut defined, &mut referenced);
+        let used = defined.intersection(&referenced).count();
+        // Nothing defined means nothing is wasted
+        metadata.usage_ratio = Some(if defined.is_empty() {
+            1.0
+        } else {
+            used as f32 / defined.len() as f32
+        });
+
+        let mut placements = HashMap::new();
+        count_placements(&swf.tags, &mut placements);
+        let reused_sprites = swf
+            .tags
+            .iter()
+            .filter(|tag| match tag {
+                swf::Tag::DefineSprite(sprite) => {
+                    placements.get(&sprite.id).is_some_and(|&count| count > 1)
+                }
+                _ => false,
+            })
+            .count();
+        metadata.reused_sprite_count = Some(reused_sprites as u32);
+    }
+
+    if options.scripts {
+        // DoInitAction runs before the first frame of the sprite it names
+        let init_actions = swf
+            .tags
+            .iter()
+            .filter_map(|tag| match tag {
+                swf::Tag::DoInitAction { id, .. } => Some(*id),
+                _ => None,
+            })
+            .collect();
+        metadata.init_actions = Some(init_actions);
+
+        // The last scale mode and alignment assigned are the ones in effect
+        let mut frame_rates = Vec::new();
+        for (property, value) in stage_assignments(swf) {
+            match (property.as_str(), value) {
+                ("scaleMode", ScriptValue::Str(value)) => metadata.scale_mode = Some(value),
+                ("align", ScriptValue::Str(value)) => metadata.stage_align = Some(value),
+                ("frameRate", ScriptValue::Number(rate))
+                    if !frame_rates.contains(&(rate as f32)) =>
+                {
+                    frame_rates.push(rate as f32)
+                }
+                _ => {}
+            }
+        }
+        metadata.scripted_frame_rates = Some(frame_rates);
+        metadata.is_preloader = Some(is_preloader(swf, &metadata));
+        metadata.has_custom_cursor = Some(has_custom_cursor(swf));
+        let mut clip_event_count = 0;
+        let mut clip_event_types = BTreeSet::new();
+        collect_clip_events(&swf.tags, &mut clip_event_count, &mut clip_event_types);
+        metadata.clip_event_count = Some(clip_event_count);
+        metadata.clip_event_types =
+            Some(clip_event_types.into_iter().map(str::to_string).collect());
+    }
+
+    if options.shapes {
+        metadata.has_offstage_content = Some(has_offstage_content(swf));
+
+        let mut morph_shapes = HashSet::new();
+        collect_morph_shapes(&swf.tags, &mut morph_shapes);
+        metadata.morph_tween_count = Some(count_morph_tweens(&swf.tags, &morph_shapes));
+        // Only DefineShape4 stores a fill rule; earlier shapes are always even-odd
+        metadata.uses_nonzero_winding = Some(swf.tags.iter().any(|tag| {
+            matches!(tag, swf::Tag::DefineShape(shape)
+                if shape.flags.contains(swf::ShapeFlag::NON_ZERO_WINDING_RULE))
+        }));
+    }
+
+    if options.audio {
+        // Zero-based indices of the root timeline frames that carry streamed audio
+        let mut sound_frames = Vec::new();
+        let mut frame = 0;
+        for tag in &swf.tags {
+            match tag {
+                swf::Tag::SoundStreamBlock(_) if sound_frames.last() != Some(&frame) => {
+                    sound_frames.push(frame);
+                }
+                swf::Tag::ShowFrame => frame += 1,
+                _ => {}
+            }
+        }
+        metadata.sound_frames = Some(sound_frames);
+        metadata.has_looping_sound = Some(has_looping_sound(&swf.tags));
+    }
+
+    if options.video {
+        let mut streams = Vec::new();
+        let mut with_frames = HashSet::new();
+        collect_video_streams(&swf.tags, &mut streams, &mut with_frames);
+        streams.retain(|id| !with_frames.contains(id));
+        metadata.empty_video_streams = Some(streams);
+    }
+
+    if options.binary_data {
+        let blobs: Vec<&swf::DefineBinaryData> = swf
+            .tags
+            .iter()
+            .filter_map(|tag| match tag {
+                swf::Tag::DefineBinaryData(binary_data) => Some(binary_data),
+                _ => None,
+            })
+            .collect();
+        metadata.binary_blob_count = Some(blobs.len() as u32);
+        metadata.binary_total_bytes = Some(blobs.iter().map(|blob| blob.data.len() as u64).sum());
+    }
+
+    if options.buttons {
+        let mut interactive = 0;
+        let mut filtered = 0;
+        let mut urls = Vec::new();
+        for tag in &swf.tags {
+            if let swf::Tag::DefineButton(button) | swf::Tag::DefineButton2(button) = tag {
+                // Only DefineButton2 records carry filters, so this stays 0 for DefineButton
+                if button
+                    .records
+                    .iter()
+                    .any(|record| !record.filters.is_empty())
+                {
+                    filtered += 1;
+                }
+                // An action list holding only the End action does nothing
+                let handlers: Vec<&[u8]> = button
+                    .actions
+                    .iter()
+                    .map(|action| action.action_data)
+                    .filter(|data| !data.is_empty() && *data != [0])
+                    .collect();
+                if !handlers.is_empty() {
+                    interactive += 1;
+                }
+                for data in handlers {
+                    collect_avm1_urls(data, swf.header.version(), &mut urls);
+                }
+            }
+        }
+        metadata.interactive_button_count = Some(interactive);
+        metadata.button_urls = Some(urls);
+        metadata.filtered_button_count = Some(filtered);
+    }
+
+    if options.classes {
+        let rsl_libraries = find_rsl_libraries(swf);
+        metadata.uses_rsl = Some(rsl_libraries.is_some());
+        metadata.rsl_libraries = Some(rsl_libraries.unwrap_or_default());
+    }
+
+    metadata
+}
+
+/// Output formats `format` can render metadata in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Json,
+    Yaml,
+    Plist,
+    Csv,
+    Text,
+    /// JSON Lines: each record is a compact JSON object on a line of its own
+    Jsonl,
+}
+
+impl Format {
+    /// The names `from_name` accepts
+    pub const NAMES: [&'static str; 6] = ["json", "jsonl", "yaml", "plist", "csv", "text"];
+
+    /// Looks a format up by name, returning `None` for unknown names.
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "json" => Some(Format::Json),
+            "jsonl" => Some(Format::Jsonl),
+            "yaml" => Some(Format::Yaml),
+            "plist" => Some(Format::Plist),
+            "csv" => Some(Format::Csv),
+            "text" => Some(Format::Text),
+            _ => None,
+        }
+    }
+
+    /// The format's name, which is also the extension of the sidecars written in it.
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Jsonl => "jsonl",
+            Format::Yaml => "yaml",
+            Format::Plist => "plist",
+            Format::Csv => "csv",
+            Format::Text => "text",
+        }
+    }
+}
+
+/// Options refining how `format_with_options` renders a format
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FormatOptions {
+    /// With `Format::Text`, squeeze each record onto one line, e.g.
+    /// `test.swf 800x600 100f @30fps`
+    pub compact_text: bool,
+    /// With `Format::Json` and `Format::Jsonl`, write `u64` fields as strings, for
+    /// consumers that read every number as a double and lose precision above 2^53
+    pub string_numbers: bool,
+    /// With `Format::Csv`, the character separating fields, `,` by default; `;` suits
+    /// spreadsheet locales that use a decimal comma
+    pub csv_delimiter: char,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            compact_text: false,
+            string_numbers: false,
+            csv_delimiter: ',',
+        }
+    }
+}
+
+/// Columns of the `csv` format
+const CSV_COLUMNS: [&str; 5] = [
+    "file_name",
+    "stage_width",
+    "stage_height",
+    "no_of_frames",
+    "frame_rate",
+];
+
+/// Renders the `csv` data row for `metadata`, without the header.
+pub fn csv_row(metadata: &Metadata, options: &FormatOptions) -> String {
+    [
+        csv_field(&metadata.file_name, options.csv_delimiter),
+        metadata.stage_size.0.to_string(),
+        metadata.stage_size.1.to_string(),
+        metadata.no_of_frames.to_string(),
+        metadata.frame_rate.to_string(),
+    ]
+    .join(&options.csv_delimiter.to_string())
+}
+
+/// Renders the `csv` row kept for a file that failed to extract. CSV has no column for
+/// the error, so the row only keeps the file in the table.
+pub fn csv_error_row(file_name: &str, options: &FormatOptions) -> String {
+    let mut row = csv_field(file_name, options.csv_delimiter);
+    for _ in 1..CSV_COLUMNS.len() {
+        row.push(options.csv_delimiter);
+    }
+    row
+}
+
+/// Joins `csv_row`s under a single header row.
+pub fn combine_csv_rows(rows: &[String], options: &FormatOptions) -> String {
+    let mut csv = csv_header(options);
+    for row in rows {
+        csv.push('\n');
+        csv.push_str(row);
+    }
+    csv
+}
+
+fn csv_header(options: &FormatOptions) -> String {
+    CSV_COLUMNS.join(&options.csv_delimiter.to_string())
+}
+
+/// Quotes a CSV field if it holds the delimiter, a quote or a line break.
+fn csv_field(value: &str, delimiter: char) -> String {
+    if value.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Writes `u64` values as JSON strings and everything else as usual
+struct StringU64Formatter;
+
+impl serde_json::ser::Formatter for StringU64Formatter {
+    fn write_u64<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: u64) -> io::Result<()> {
+        write!(writer, "\"{}\"", value)
+    }
+}
+
+/// Renders `metadata` in `format`.
+pub fn format(metadata: &Metadata, format: Format) -> String {
+    format_with_options(metadata, format, &FormatOptions::default())
+}
+
+/// Renders `metadata` in `format`, refined by `options`.
+pub fn format_with_options(metadata: &Metadata, format: Format, options: &FormatOptions) -> String {
+    if format == Format::Text && options.compact_text {
+        format!(
+            "{} {}x{} {}f @{}fps",
+            metadata.file_name,
+            metadata.stage_size.0,
+            metadata.stage_size.1,
+            metadata.no_of_frames,
+            metadata.frame_rate
+        )
+    } else if format == Format::Csv {
+        combine_csv_rows(&[csv_row(metadata, options)], options)
+    } else if matches!(format, Format::Json | Format::Jsonl) && !options.string_numbers {
+        serde_json::to_string(metadata).unwrap()
+    } else if matches!(format, Format::Json | Format::Jsonl) {
+        let mut json = Vec::new();
+        let mut serializer = serde_json::Serializer::with_formatter(&mut json, StringU64Formatter);
+        serde::Serialize::serialize(metadata, &mut serializer).unwrap();
+        String::from_utf8(json).unwrap()
+    } else if format == Format::Yaml {
+        serde_yaml::to_string(metadata).unwrap()
+    } else if format == Format::Plist {
+        let mut plist = Vec::new();
+        plist::to_writer_xml(&mut plist, metadata).unwrap();
+        String::from_utf8(plist).unwrap()
+    } else {
+        let mut text = String::new();
+        if let Some(version) = metadata.schema_version {
+            text.push_str(&format!("Schema Version: {}\n", version));
+        }
+        text.push_str(&format!("File: {}", metadata.file_name));
+        text.push_str(&format!("\nStage Size: {:?}", metadata.stage_size));
+        text.push_str(&format!(
+            "\nStage Size (Precise): {:?}",
+            metadata.stage_size_precise
+        ));
+        text.push_str(&format!(
+            "\nStage Size (Twips): {:?}",
+            metadata.stage_size_twips
+        ));
+        text.push_str(&format!("\nNumber of Frames: {}", metadata.no_of_frames));
+        text.push_str(&format!("\nFrame Rate: {}", metadata.frame_rate));
+        text.push_str(&format!(
+            "\nMetadata Flag Consistent: {}",
+            metadata.metadata_flag_consistent
+        ));
+        text.push_str(&format!("\nSWF Version: {}", metadata.swf_version));
+        text.push_str(&format!("\nCompression: {}", metadata.compression));
+        text.push_str(&format!("\nFile Size: {} bytes", metadata.file_size_bytes));
+        text.push_str(&format!(
+            "\nUncompressed Size: {} bytes",
+            metadata.uncompressed_size_bytes
+        ));
+        text.push_str(&format!(
+            "\nCompression Ratio: {:.2}",
+            metadata.compression_ratio
+        ));
+        text.push_str(&format!("\nSHA-256: {}", metadata.sha256));
+        let color = metadata.background_color.as_deref().unwrap_or("none");
+        text.push_str(&format!("\nBackground Color: {}", color));
+        let version = metadata
+            .actionscript_version
+            .map_or("none".to_string(), |version| version.to_string());
+        text.push_str(&format!("\nActionScript Version: {}", version));
+        let xmp = metadata
+            .xmp_metadata
+            .as_ref()
+            .map_or("none".to_string(), |xmp| format!("{} bytes", xmp.len()));
+        text.push_str(&format!("\nXMP Metadata: {}", xmp));
+        let first = metadata
+            .file_attributes_first
+            .map_or("none".to_string(), |first| first.to_string());
+        text.push_str(&format!("\nFileAttributes First: {}", first));
+        text.push_str(&format!("\nMixed AVM1/AVM2: {}", metadata.mixed_avm));
+        text.push_str(&format!(
+            "\nHeader Anomalies: {}",
+            metadata.header_anomalies.len()
+        ));
+        for anomaly in &metadata.header_anomalies {
+            text.push_str(&format!("\n  - {}", anomaly));
+        }
+        if let Some(warning) = &metadata.frame_rate_warning {
+            text.push_str(&format!("\nFrame Rate Warning: {}", warning));
+        }
+        if let Some(frame_rate) = metadata.normalized_frame_rate {
+            text.push_str(&format!("\nNormalized Frame Rate: {}", frame_rate));
+        }
+        if let Some(warnings) = &metadata.parse_warnings {
+            text.push_str(&format!("\nParse Warnings: {}", warnings.len()));
+            for warning in warnings {
+                text.push_str(&format!("\n  - {}", warning));
+            }
+        }
+        if let Some(imports) = &metadata.broken_imports {
+            text.push_str(&format!("\nBroken Imports: {}", imports.len()));
+            for import in imports {
+                text.push_str(&format!("\n  - {}", import));
+            }
+        }
+        if let Some(max_depth) = metadata.max_depth_used {
+            text.push_str(&format!("\nMax Depth Used: {}", max_depth));
+        }
+        if let Some(depths) = metadata.distinct_depths {
+            text.push_str(&format!("\nDistinct Depths: {}", depths));
+        }
+        if let Some(modes) = &metadata.blend_modes {
+            text.push_str(&format!("\nBlend Modes: {}", modes.join(", ")));
+        }
+        if let Some(count) = metadata.cache_as_bitmap_count {
+            text.push_str(&format!("\nCached As Bitmap: {}", count));
+        }
+        if let Some(has_cff_fonts) = metadata.has_cff_fonts {
+            text.push_str(&format!("\nHas CFF Fonts: {}", has_cff_fonts));
+        }
+        if let Some(names) = &metadata.cff_font_names {
+            text.push_str(&format!("\nCFF Fonts: {}", names.join(", ")));
+        }
+        if let Some(copyrights) = &metadata.font_copyrights {
+            text.push_str(&format!("\nFont Copyrights: {}", copyrights.len()));
+            for copyright in copyrights {
+                text.push_str(&format!("\n  - {}", copyright));
+            }
+        }
+        if let Some(count) = metadata.total_glyph_count {
+            text.push_str(&format!("\nTotal Glyphs: {}", count));
+        }
+        if let Some(bytes) = metadata.estimated_bitmap_memory_bytes {
+            text.push_str(&format!("\nEstimated Bitmap Memory: {} bytes", bytes));
+        }
+        if let Some(formats) = &metadata.bitmap_formats {
+            let counts: Vec<String> = formats
+                .iter()
+                .map(|(format, count)| format!("{}: {}", format, count))
+                .collect();
+            text.push_str(&format!("\nBitmap Formats: {}", counts.join(", ")));
+        }
+        if let Some(count) = metadata.empty_sprite_count {
+            text.push_str(&format!("\nEmpty Sprites: {}", count));
+        }
+        if let Some(ratio) = metadata.usage_ratio {
+            text.push_str(&format!("\nCharacter Usage Ratio: {:.2}", ratio));
+        }
+        if let Some(count) = metadata.reused_sprite_count {
+            text.push_str(&format!("\nReused Sprites: {}", count));
+        }
+        if let Some(ids) = &metadata.init_actions {
+            text.push_str(&format!("\nInit Actions: {:?}", ids));
+        }
+        if let Some(scale_mode) = &metadata.scale_mode {
+            text.push_str(&format!("\nScale Mode: {}", scale_mode));
+        }
+        if let Some(align) = &metadata.stage_align {
+            text.push_str(&format!("\nStage Align: {}", align));
+        }
+        if let Some(rates) = &metadata.scripted_frame_rates {
+            text.push_str(&format!("\nScripted Frame Rates: {:?}", rates));
+        }
+        if let Some(preloader) = metadata.is_preloader {
+            text.push_str(&format!("\nIs Preloader: {}", preloader));
+        }
+        if let Some(custom_cursor) = metadata.has_custom_cursor {
+            text.push_str(&format!("\nHas Custom Cursor: {}", custom_cursor));
+        }
+        if let Some(count) = metadata.clip_event_count {
+            text.push_str(&format!("\nClip Event Handlers: {}", count));
+        }
+        if let Some(events) = &metadata.clip_event_types {
+            text.push_str(&format!("\nClip Event Types: {}", events.join(", ")));
+        }
+        if let Some(offstage) = metadata.has_offstage_content {
+            text.push_str(&format!("\nHas Offstage Content: {}", offstage));
+        }
+        if let Some(count) = metadata.morph_tween_count {
+            text.push_str(&format!("\nMorph Tweens: {}", count));
+        }
+        if let Some(nonzero) = metadata.uses_nonzero_winding {
+            text.push_str(&format!("\nUses Nonzero Winding: {}", nonzero));
+        }
+        if let Some(frames) = &metadata.sound_frames {
+            text.push_str(&format!("\nSound Frames: {:?}", frames));
+        }
+        if let Some(looping) = metadata.has_looping_sound {
+            text.push_str(&format!("\nHas Looping Sound: {}", looping));
+        }
+        if let Some(streams) = &metadata.empty_video_streams {
+            text.push_str(&format!("\nEmpty Video Streams: {:?}", streams));
+        }
+        if let Some(count) = metadata.binary_blob_count {
+            text.push_str(&format!("\nBinary Blobs: {}", count));
+        }
+        if let Some(bytes) = metadata.binary_total_bytes {
+            text.push_str(&format!("\nBinary Data Size: {} bytes", bytes));
+        }
+        if let Some(count) = metadata.interactive_button_count {
+            text.push_str(&format!("\nInteractive Buttons: {}", count));
+        }
+        if let Some(urls) = &metadata.button_urls {
+            text.push_str(&format!("\nButton URLs: {}", urls.join(", ")));
+        }
+        if let Some(count) = metadata.filtered_button_count {
+            text.push_str(&format!("\nFiltered Buttons: {}", count));
+        }
+        if let Some(uses_rsl) = metadata.uses_rsl {
+            text.push_str(&format!("\nUses RSL: {}", uses_rsl));
+        }
+        if let Some(libraries) = &metadata.rsl_libraries {
+            text.push_str(&format!("\nRSL Libraries: {}", libraries.join(", ")));
+        }
+        if let Some(count) = metadata.long_tag_count {
+            text.push_str(&format!("\nLong Tags: {}", count));
+        }
+        if let Some(version) = metadata.min_version_by_tags {
+            text.push_str(&format!("\nMinimum Version By Tags: {}", version));
+        }
+        if let Some(histogram) = &metadata.tag_size_histogram {
+            let buckets: Vec<String> = histogram
+                .iter()
+                .map(|(bucket, count)| format!("{}: {}", bucket, count))
+                .collect();
+            text.push_str(&format!("\nTag Size Histogram: {}", buckets.join(", ")));
+        }
+        if let Some(count) = metadata.tags_after_last_frame {
+            text.push_str(&format!("\nTags After Last Frame: {}", count));
+        }
+        if let Some(counts) = &metadata.tag_counts {
+            let counts: Vec<String> = counts
+                .iter()
+                .map(|(name, count)| format!("{}: {}", name, count))
+                .collect();
+            text.push_str(&format!("\nTag Counts: {}", counts.join(", ")));
+        }
+        if let Some(descriptions) = &metadata.tag_descriptions {
+            text.push_str("\nTag Descriptions:");
+            for (name, description) in descriptions {
+                text.push_str(&format!("\n  - {}: {}", name, description));
+            }
+        }
+        if let Some(duration) = metadata.parse_duration_ms {
+            text.push_str(&format!("\nParse Duration: {:.3} ms", duration));
+        }
+        text
+    }
+}
+
+/// Renders the record written in place of metadata for a file that failed to extract.
+pub fn format_error_placeholder(
+    file_name: &str,
+    error: &str,
+    format: Format,
+    options: &FormatOptions,
+) -> String {
+    let placeholder = ErrorPlaceholder { file_name, error };
+    if format == Format::Csv {
+        combine_csv_rows(&[csv_error_row(file_name, options)], options)
+    } else if format == Format::Text && options.compact_text {
+        format!("{} error: {}", file_name, error)
+    } else if matches!(format, Format::Json | Format::Jsonl) {
+        serde_json::to_string(&placeholder).unwrap()
+    } else if format == Format::Yaml {
+        serde_yaml::to_string(&placeholder).unwrap()
+    } else if format == Format::Plist {
+        let mut plist = Vec::new();
+        plist::to_writer_xml(&mut plist, &placeholder).unwrap();
+        String::from_utf8(plist).unwrap()
+    } else {
+        format!("File: {}\nError: {}", file_name, error)
+    }
+}
+
+/// Joins per-file outputs into the single document printed by `--stdout` in directory
+/// mode: an array for JSON and plist, a sequence for YAML, one header followed by every
+/// row for CSV, one line per file for compact text and JSON Lines, blank-line separated
+/// text otherwise.
+pub fn combine_outputs(outputs: &[String], format: Format, options: &FormatOptions) -> String {
+    if format == Format::Csv {
+        // Strip each output's own header; splitting on lines would break quoted line breaks
+        let header = format!("{}\n", csv_header(options));
+        let rows: Vec<String> = outputs
+            .iter()
+            .map(|output| output.strip_prefix(&header).unwrap_or(output).to_string())
+            .collect();
+        combine_csv_rows(&rows, options)
+    } else if format == Format::Jsonl || (format == Format::Text && options.compact_text) {
+        outputs.join("\n")
+    } else if format == Format::Json {
+        // Each output is already a complete JSON value, so joining keeps their field order
+        format!("[{}]", outputs.join(","))
+    } else if format == Format::Yaml {
+        let values: Vec<serde_yaml::Value> = outputs
+            .iter()
+            .map(|output| serde_yaml::from_str(output).unwrap())
+            .collect();
+        serde_yaml::to_string(&values).unwrap()
+    } else if format == Format::Plist {
+        let values = outputs
+            .iter()
+            .map(|output| plist::Value::from_reader_xml(output.as_bytes()).unwrap())
+            .collect();
+        let mut plist = Vec::new();
+        plist::Value::Array(values)
+            .to_writer_xml(&mut plist)
+            .unwrap();
+        String::from_utf8(plist).unwrap()
+    } else {
+        outputs.join("\n\n")
+    }
+}
+
+fn trim_file_name(file_name: &str, prefix: &Path) -> String {
+    match Path::new(file_name).strip_prefix(prefix) {
+        Ok(trimmed) => trimmed.to_string_lossy().into_owned(),
+        Err(_) => file_name.to_string(),
+    }
+}
+
+/// Reads the header of the SWF at `path` as the `swf` crate parses it, including the
+/// fields `Metadata` doesn't project such as the `FileAttributes` flags. The tag stream
+/// is decompressed but not parsed.
+///
+/// ```
+/// # let path = std::env::temp_dir().join("extract_metadata_read_header.swf");
+/// # let header = swf::Header {
+/// #     compression: swf::Compression::None,
+/// #     version: 10,
+/// #     stage_size: swf::Rectangle {
+/// #         x_min: swf::Twips::ZERO,
+/// #         x_max: swf::Twips::from_pixels(550.0),
+/// #         y_min: swf::Twips::ZERO,
+/// #         y_max: swf::Twips::from_pixels(400.0),
+/// #     },
+/// #     frame_rate: swf::Fixed8::from_f32(24.0),
+/// #     num_frames: 1,
+/// # };
+/// # swf::write_swf(&header, &[swf::Tag::ShowFrame], std::fs::File::create(&path)?).unwrap();
+/// let header = extract_metadata::read_header(&path)?;
+/// assert_eq!(header.version(), 10);
+/// assert_eq!(header.num_frames(), 1);
+/// # Ok::<(), extract_metadata::ExtractError>(())
+/// ```
+pub fn read_header(path: &Path) -> Result<swf::HeaderExt, ExtractError> {
+    let file = File::open(path)?;
+    let swf_buf = swf::decompress_swf(BufReader::new(file)).map_err(ExtractError::Decompress)?;
+    Ok(swf_buf.header)
+}
+
+/// Checks that the SWF at `path` decompresses and parses, without extracting anything.
+pub fn validate_swf(path: &Path) -> Result<(), ExtractError> {
+    let file = File::open(path)?;
+    let swf_buf = swf::decompress_swf(BufReader::new(file)).map_err(ExtractError::Decompress)?;
+    swf::parse_swf(&swf_buf).map_err(ExtractError::Parse)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Helper function to install `WarningCollector` once for the tests that read warnings
+    fn install_warning_collector() {
+        static INSTALL: std::sync::Once = std::sync::Once::new();
+        INSTALL.call_once(|| {
+            log::set_logger(&WarningCollector).unwrap();
+            log::set_max_level(log::LevelFilter::Warn);
+        });
+    }
+
+    // Helper function to create test metadata
+    fn create_test_metadata() -> Metadata {
+        Metadata {
+            file_name: "test.swf".to_string(),
+            stage_size: (800, 600),
+            stage_size_precise: (800.0, 600.0),
+            stage_size_twips: (16000, 12000),
+            no_of_frames: 100,
+            frame_rate: 30.0,
+            metadata_flag_consistent: true,
+            swf_version: 10,
+            compression: "none".to_string(),
+            ..Default::default()
+        }
+    }
+
+    // Helper function to build an uncompressed in-memory SWF from a list of tags
+    fn create_test_swf(tags: &[swf::Tag]) -> Vec<u8> {
+        create_test_swf_with_header(&create_test_header(), tags)
+    }
+
+    // Helper function to create the SWF header matching `create_test_metadata`
+    fn create_test_header() -> swf::Header {
+        swf::Header {
+            compression: swf::Compression::None,
+            version: 10,
+            stage_size: swf::Rectangle {
+                x_min: swf::Twips::ZERO,
+                x_max: swf::Twips::from_pixels(800.0),
+                y_min: swf::Twips::ZERO,
+                y_max: swf::Twips::from_pixels(600.0),
+            },
+            frame_rate: swf::Fixed8::from_f32(30.0),
+            num_frames: 100,
+        }
+    }
+
+    fn create_test_swf_with_header(header: &swf::Header, tags: &[swf::Tag]) -> Vec<u8> {
+        let mut data = Vec::new();
+        swf::write_swf(header, tags, &mut data).unwrap();
+        data
+    }
+
+    // Helper function to run metadata extraction over an in-memory SWF
+    fn metadata_from_tags(tags: &[swf::Tag]) -> Metadata {
+        metadata_from_tags_with(tags, &ExtractOptions::default())
+    }
+
+    fn metadata_from_tags_with(tags: &[swf::Tag], options: &ExtractOptions) -> Metadata {
+        let data = create_test_swf(tags);
+        read_metadata("test.swf", &data[..], options).unwrap()
+    }
+
+    // Helper function to create a PlaceObject2 tag placing a character at a depth
+    fn place_object(id: u16, depth: u16) -> swf::Tag<'static> {
+        swf::Tag::PlaceObject(Box::new(placement(id, depth)))
+    }
+
+    // Helper function to create a PlaceObject2 record for tests that customize it
+    fn placement(id: u16, depth: u16) -> swf::PlaceObject<'static> {
+        swf::PlaceObject {
+            version: 2,
+            action: swf::PlaceObjectAction::Place(id),
+            depth,
+            matrix: None,
+            color_transform: None,
+            ratio: None,
+            name: None,
+            clip_depth: None,
+            class_name: None,
+            filters: None,
+            background_color: None,
+            blend_mode: None,
+            clip_actions: None,
+            has_image: false,
+            is_bitmap_cached: None,
+            is_visible: None,
+            amf_data: None,
+        }
+    }
+
+    #[test]
+    fn test_metadata_to_json() {
+        let metadata = create_test_metadata();
+        let result = format(&metadata, Format::Json);
+
+        // Verify it's valid JSON
+        assert!(result.contains("\"file_name\":\"test.swf\""));
+        assert!(result.contains("\"stage_size\":[800,600]"));
+        assert!(result.contains("\"no_of_frames\":100"));
+        assert!(result.contains("\"frame_rate\":30.0"));
+
+        // Verify it can be parsed back
+        let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(parsed["file_name"], "test.swf");
+        assert_eq!(parsed["stage_size"][0], 800);
+        assert_eq!(parsed["stage_size"][1], 600);
+    }
+
+    #[test]
+    fn test_metadata_to_yaml() {
+        let metadata = create_test_metadata();
+        let result = format(&metadata, Format::Yaml);
+
+        // Verify YAML format
+        assert!(result.contains("file_name: test.swf"));
+        assert!(result.contains("stage_size:"));
+        assert!(result.contains("- 800"));
+        assert!(result.contains("- 600"));
+        assert!(result.contains("no_of_frames: 100"));
+        assert!(result.contains("frame_rate: 30.0"));
+    }
+
+    #[test]
+    fn test_metadata_to_plist() {
+        let metadata = create_test_metadata();
+        let result = format(&metadata, Format::Plist);
+
+        // Verify the plist parses back with the expected values
+        let value = plist::Value::from_reader_xml(result.as_bytes()).unwrap();
+        let dict = value.as_dictionary().unwrap();
+        assert_eq!(dict["file_name"].as_string(), Some("test.swf"));
+        let stage_size = dict["stage_size"].as_array().unwrap();
+        assert_eq!(stage_size[0].as_unsigned_integer(), Some(800));
+        assert_eq!(stage_size[1].as_unsigned_integer(), Some(600));
+        assert_eq!(dict["no_of_frames"].as_unsigned_integer(), Some(100));
+        assert_eq!(dict["frame_rate"].as_real(), Some(30.0));
+    }
+
+    #[test]
+    fn test_metadata_to_text() {
+        let metadata = create_test_metadata();
+        let result = format(&metadata, Format::Text);
+
+        // Verify text format
+        assert!(result.contains("File: test.swf"));
+        assert!(result.contains("Stage Size: (800, 600)"));
+        assert!(result.contains("Number of Frames: 100"));
+        assert!(result.contains("Frame Rate: 30"));
+        assert!(result.contains("SWF Version: 10"));
+        assert!(result.contains("Compression: none"));
+    }
+
+    #[test]
+    fn test_swf_version_and_compression() {
+        let metadata = metadata_from_tags(&[swf::Tag::ShowFrame]);
+        assert_eq!(metadata.swf_version, 10);
+        assert_eq!(metadata.compression, "none");
+
+        let header = swf::Header {
+            compression: swf::Compression::Zlib,
+            version: 9,
+            ..create_test_header()
+        };
+        let data = create_test_swf_with_header(&header, &[swf::Tag::ShowFrame]);
+        assert_eq!(&data[..3], b"CWS");
+        let metadata = read_metadata("test.swf", &data[..], &ExtractOptions::default()).unwrap();
+        assert_eq!(metadata.swf_version, 9);
+        assert_eq!(metadata.compression, "zlib");
+
+        let json = format(&metadata, Format::Json);
+        assert!(json.contains("\"swf_version\":9,\"compression\":\"zlib\""));
+    }
+
+    #[test]
+    fn test_file_and_uncompressed_sizes() {
+        let payload = [0; 4096];
+        let tags = [
+            swf::Tag::DefineBinaryData(swf::DefineBinaryData {
+                id: 1,
+                data: &payload,
+            }),
+            swf::Tag::ShowFrame,
+        ];
+        let data = create_test_swf(&tags);
+        let metadata = read_metadata("test.swf", &data[..], &ExtractOptions::default()).unwrap();
+        assert_eq!(metadata.file_size_bytes, data.len() as u64);
+        assert_eq!(metadata.uncompressed_size_bytes, data.len() as u64);
+        assert_eq!(metadata.compression_ratio, 1.0);
+
+        let header = swf::Header {
+            compression: swf::Compression::Zlib,
+            ..create_test_header()
+        };
+        let data = create_test_swf_with_header(&header, &tags);
+        let metadata = read_metadata("test.swf", &data[..], &ExtractOptions::default()).unwrap();
+        assert_eq!(metadata.file_size_bytes, data.len() as u64);
+        assert!(metadata.uncompressed_size_bytes > metadata.file_size_bytes);
+        assert!(metadata.compression_ratio > 1.0);
+    }
+
+    #[test]
+    fn test_background_color() {
+        let metadata = metadata_from_tags(&[
+            swf::Tag::SetBackgroundColor(swf::Color::from_rgb(0xFF0000, 255)),
+            swf::Tag::ShowFrame,
+        ]);
+        assert_eq!(metadata.background_color, Some("#FF0000".to_string()));
+
+        // Flex puts FileAttributes, Metadata and ScriptLimits ahead of the color
+        let metadata = metadata_from_tags(&[
+            swf::Tag::FileAttributes(swf::FileAttributes::HAS_METADATA),
+            swf::Tag::Metadata(swf::SwfStr::from_utf8_str("<x:xmpmeta/>")),
+            swf::Tag::ScriptLimits {
+                max_recursion_depth: 1000,
+                timeout_in_seconds: 60,
+            },
+            swf::Tag::SetBackgroundColor(swf::Color::from_rgb(0x336699, 255)),
+            swf::Tag::ShowFrame,
+        ]);
+        assert_eq!(metadata.background_color, Some("#336699".to_string()));
+
+        // Without the tag the color is unknown rather than black
+        let metadata = metadata_from_tags(&[swf::Tag::ShowFrame]);
+        assert_eq!(metadata.background_color, None);
+        assert!(format(&metadata, Format::Json).contains("\"background_color\":null"));
+    }
+
+    #[test]
+    fn test_xmp_metadata() {
+        let xmp = "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"><dc:title>Intro</dc:title></x:xmpmeta>";
+        let metadata = metadata_from_tags(&[
+            swf::Tag::Metadata(swf::SwfStr::from_utf8_str(xmp)),
+            swf::Tag::ShowFrame,
+        ]);
+        assert!(
+            metadata
+                .xmp_metadata
+                .is_some_and(|packet| packet.contains("<dc:title>Intro</dc:title>"))
+        );
+
+        let metadata = metadata_from_tags(&[swf::Tag::ShowFrame]);
+        assert_eq!(metadata.xmp_metadata, None);
+    }
+
+    #[test]
+    fn test_file_attributes_first() {
+        let file_attributes = || swf::Tag::FileAttributes(swf::FileAttributes::empty());
+        let metadata = metadata_from_tags(&[file_attributes(), swf::Tag::ShowFrame]);
+        assert_eq!(metadata.file_attributes_first, Some(true));
+
+        let metadata = metadata_from_tags(&[
+            swf::Tag::SetBackgroundColor(swf::Color::from_rgb(0xFFFFFF, 255)),
+            file_attributes(),
+            swf::Tag::ShowFrame,
+        ]);
+        assert_eq!(metadata.file_attributes_first, Some(false));
+
+        let metadata = metadata_from_tags(&[swf::Tag::ShowFrame]);
+        assert_eq!(metadata.file_attributes_first, None);
+    }
+
+    #[test]
+    fn test_actionscript_version() {
+        use swf::avm2::types::Op;
+
+        let abc = abc_file(&[], &[Op::ReturnVoid]);
+        let metadata = metadata_from_tags(&[swf::Tag::DoAbc(&abc), swf::Tag::ShowFrame]);
+        assert_eq!(metadata.actionscript_version, Some(3));
+
+        // AVM1 actions nested in a sprite still make the file ActionScript 1/2
+        let actions = avm1_actions(&[swf::avm1::types::Action::End]);
+        let metadata = metadata_from_tags(&[
+            swf::Tag::DefineSprite(swf::Sprite {
+                id: 1,
+                num_frames: 1,
+                tags: vec![swf::Tag::DoAction(&actions), swf::Tag::ShowFrame],
+            }),
+            swf::Tag::ShowFrame,
+        ]);
+        assert_eq!(metadata.actionscript_version, Some(1));
+
+        // A pure timeline animation has no scripts
+        let metadata = metadata_from_tags(&[place_object(1, 1), swf::Tag::ShowFrame]);
+        assert_eq!(metadata.actionscript_version, None);
+    }
+
+    #[test]
+    fn test_fractional_frame_rate() {
+        let header = swf::Header {
+            frame_rate: swf::Fixed8::from_f32(12.5),
+            ..create_test_header()
+        };
+        let data = create_test_swf_with_header(&header, &[swf::Tag::ShowFrame]);
+        let metadata = read_metadata("test.swf", &data[..], &ExtractOptions::default()).unwrap();
+        assert_eq!(metadata.frame_rate, 12.5);
+
+        let json = format(&metadata, Format::Json);
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed["frame_rate"].as_f64(), Some(12.5));
+    }
+
+    #[test]
+    fn test_normalize_frame_rate() {
+        assert_eq!(normalize_frame_rate(29.97), 30.0);
+        assert_eq!(normalize_frame_rate(23.976), 24.0);
+        assert_eq!(normalize_frame_rate(25.0), 25.0);
+        assert_eq!(normalize_frame_rate(15.0), 15.0);
+
+        // The header stores 29.97 as 8.8 fixed point, 29.96875
+        let header = swf::Header {
+            frame_rate: swf::Fixed8::from_f32(29.97),
+            ..create_test_header()
+        };
+        let data = create_test_swf_with_header(&header, &[swf::Tag::ShowFrame]);
+        let options = ExtractOptions {
+            normalize_frame_rate: true,
+            ..Default::default()
+        };
+        let metadata = read_metadata("test.swf", &data[..], &options).unwrap();
+        assert_eq!(metadata.normalized_frame_rate, Some(30.0));
+        assert_eq!(
+            metadata_from_tags(&[swf::Tag::ShowFrame]).normalized_frame_rate,
+            None
+        );
+    }
+
+    #[test]
+    fn test_frame_rate_warning() {
+        let header = swf::Header {
+            frame_rate: swf::Fixed8::ZERO,
+            ..create_test_header()
+        };
+        let data = create_test_swf_with_header(&header, &[swf::Tag::ShowFrame]);
+        let metadata = read_metadata("test.swf", &data[..], &ExtractOptions::default()).unwrap();
+        assert_eq!(
+            metadata.frame_rate_warning,
+            Some("frame rate is 0".to_string())
+        );
+
+        // The header's 8.8 fixed-point rate tops out below 256, so 1000 fps can only
+        // be checked against the helper directly
+        assert_eq!(
+            frame_rate_warning(1000.0, DEFAULT_MAX_SANE_FPS),
+            Some("frame rate 1000 exceeds the sane maximum of 120".to_string())
+        );
+
+        // A plain 30 fps file is fine by default but not under a stricter limit
+        assert_eq!(
+            metadata_from_tags(&[swf::Tag::ShowFrame]).frame_rate_warning,
+            None
+        );
+        let options = ExtractOptions {
+            max_sane_fps: Some(24.0),
+            ..Default::default()
+        };
+        let metadata = metadata_from_tags_with(&[swf::Tag::ShowFrame], &options);
+        assert!(metadata.frame_rate_warning.is_some());
+    }
+
+    #[test]
+    fn test_metadata_to_csv() {
+        let mut metadata = create_test_metadata();
+        metadata.file_name = "dir/a, b.swf".to_string();
+        let result = format(&metadata, Format::Csv);
+
+        let rows: Vec<&str> = result.lines().collect();
+        assert_eq!(
+            rows[0].split(',').collect::<Vec<_>>(),
+            vec![
+                "file_name",
+                "stage_width",
+                "stage_height",
+                "no_of_frames",
+                "frame_rate"
+            ]
+        );
+        assert_eq!(rows[1], "\"dir/a, b.swf\",800,600,100,30");
+        assert_eq!(rows.len(), 2);
+    }
+
+    #[test]
+    fn test_combine_csv_keeps_quoted_line_breaks() {
+        let split = Metadata {
+            file_name: "a\nb.swf".to_string(),
+            ..create_test_metadata()
+        };
+        let outputs = [
+            format(&split, Format::Csv),
+            format(&create_test_metadata(), Format::Csv),
+        ];
+        let options = FormatOptions::default();
+        assert_eq!(
+            combine_outputs(&outputs, Format::Csv, &options),
+            combine_csv_rows(
+                &[
+                    csv_row(&split, &options),
+                    csv_row(&create_test_metadata(), &options)
+                ],
+                &options
+            )
+        );
+        assert!(csv_row(&split, &options).starts_with("\"a\nb.swf\","));
+    }
+
+    #[test]
+    fn test_csv_delimiter() {
+        let metadata = Metadata {
+            file_name: "a;b.swf".to_string(),
+            ..create_test_metadata()
+        };
+        let options = FormatOptions {
+            csv_delimiter: ';',
+            ..Default::default()
+        };
+        assert_eq!(
+            format_with_options(&metadata, Format::Csv, &options),
+            "file_name;stage_width;stage_height;no_of_frames;frame_rate\n\
+             \"a;b.swf\";800;600;100;30"
+        );
+        // A comma is no longer special
+        let metadata = Metadata {
+            file_name: "a,b.swf".to_string(),
+            ..create_test_metadata()
+        };
+        assert!(csv_row(&metadata, &options).starts_with("a,b.swf;"));
+        assert_eq!(csv_error_row("x.swf", &options), "x.swf;;;;");
+    }
+
+    #[test]
+    fn test_metadata_equality() {
+        let metadata1 = create_test_metadata();
+        let metadata2 = Metadata {
+            file_name: "test.swf".to_string(),
+            stage_size: (800, 600),
+            stage_size_precise: (800.0, 600.0),
+            stage_size_twips: (16000, 12000),
+            no_of_frames: 100,
+            frame_rate: 30.0,
+            metadata_flag_consistent: true,
+            swf_version: 10,
+            compression: "none".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(metadata1, metadata2);
+    }
+
+    #[test]
+    fn test_metadata_inequality() {
+        let metadata1 = create_test_metadata();
+        let metadata2 = Metadata {
+            file_name: "different.swf".to_string(),
+            stage_size: (800, 600),
+            stage_size_precise: (800.0, 600.0),
+            stage_size_twips: (16000, 12000),
+            no_of_frames: 100,
+            frame_rate: 30.0,
+            metadata_flag_consistent: true,
+            swf_version: 10,
+            compression: "none".to_string(),
+            ..Default::default()
+        };
+        assert_ne!(metadata1, metadata2);
+    }
+
+    #[test]
+    fn test_metadata_to_compact_text() {
+        let metadata = create_test_metadata();
+        let options = FormatOptions {
+            compact_text: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_with_options(&metadata, Format::Text, &options),
+            "test.swf 800x600 100f @30fps"
+        );
+    }
+
+    #[test]
+    fn test_json_string_numbers() {
+        let metadata = Metadata {
+            binary_total_bytes: Some(u64::MAX),
+            ..create_test_metadata()
+        };
+        let options = FormatOptions {
+            string_numbers: true,
+            ..Default::default()
+        };
+        let parsed: serde_json::Value =
+            serde_json::from_str(&format_with_options(&metadata, Format::Json, &options)).unwrap();
+        assert_eq!(parsed["binary_total_bytes"], "18446744073709551615");
+        // Only u64 fields are quoted
+        assert_eq!(parsed["no_of_frames"], 100);
+
+        let line = format_with_options(&metadata, Format::Jsonl, &options);
+        assert!(!line.contains('\n'));
+        let parsed: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(parsed["binary_total_bytes"], "18446744073709551615");
+
+        let parsed: serde_json::Value =
+            serde_json::from_str(&format(&metadata, Format::Json)).unwrap();
+        assert_eq!(parsed["binary_total_bytes"], u64::MAX);
+    }
+
+    #[test]
+    fn test_jsonl_writes_one_object_per_line() {
+        let other = Metadata {
+            file_name: "other.swf".to_string(),
+            ..create_test_metadata()
+        };
+        let outputs = [
+            format(&create_test_metadata(), Format::Jsonl),
+            format(&other, Format::Jsonl),
+        ];
+        let combined = combine_outputs(&outputs, Format::Jsonl, &FormatOptions::default());
+        let lines: Vec<serde_json::Value> = combined
+            .split('\n')
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["file_name"], "test.swf");
+        assert_eq!(lines[1]["file_name"], "other.swf");
+    }
+
+    #[test]
+    fn test_format_from_name_rejects_unknown_names() {
+        for name in Format::NAMES {
+            assert_eq!(Format::from_name(name).map(Format::name), Some(name));
+        }
+        assert_eq!(Format::from_name("jsom"), None);
+        assert_eq!(Format::from_name("unknown_format"), None);
+    }
+
+    #[test]
+    fn test_build_metadata_from_swf() {
+        let metadata = metadata_from_tags(&[swf::Tag::ShowFrame]);
+        // 25 bytes on disk: the 21-byte header, then ShowFrame and End
+        let expected = Metadata {
+            file_size_bytes: 25,
+            uncompressed_size_bytes: 25,
+            compression_ratio: 1.0,
+            sha256: "91f7d57be10225d8668f9bd20d55737c275e360212f0321537271366e9fc0bc6".to_string(),
+            ..create_test_metadata()
+        };
+        assert_eq!(metadata, expected);
+    }
+
+    #[test]
+    fn test_metadata_flag_set_without_metadata_tag() {
+        let metadata = metadata_from_tags(&[
+            swf::Tag::FileAttributes(swf::FileAttributes::HAS_METADATA),
+            swf::Tag::ShowFrame,
+        ]);
+        assert!(!metadata.metadata_flag_consistent);
+    }
+
+    #[test]
+    fn test_metadata_flag_matches_metadata_tag() {
+        let xmp = swf::SwfStr::from_utf8_str("<x:xmpmeta/>");
+        let metadata = metadata_from_tags(&[
+            swf::Tag::FileAttributes(swf::FileAttributes::HAS_METADATA),
+            swf::Tag::Metadata(xmp),
+            swf::Tag::ShowFrame,
+        ]);
+        assert!(metadata.metadata_flag_consistent);
+    }
+
+    #[test]
+    fn test_parse_warnings_captured() {
+        install_warning_collector();
+        let mut data = create_test_swf(&[swf::Tag::ShowFrame]);
+        // Overstate the uncompressed length in the header so the swf crate warns about it
+        let declared_len = u32::from_le_bytes(data[4..8].try_into().unwrap());
+        data[4..8].copy_from_slice(&(declared_len + 16).to_le_bytes());
+
+        let options = ExtractOptions {
+            warnings: true,
+            ..Default::default()
+        };
+        let metadata = read_metadata("test.swf", &data[..], &options).unwrap();
+        let warnings = metadata.parse_warnings.unwrap();
+        assert!(
+            warnings
+                .iter()
+                .any(|w| w.contains("length doesn't match header"))
+        );
+    }
+
+    #[test]
+    fn test_header_anomalies_reserved_bits() {
+        // Reported without --warnings
+        let options = ExtractOptions::default();
+        let tags = [
+            swf::Tag::FileAttributes(swf::FileAttributes::USE_NETWORK_SANDBOX),
+            swf::Tag::ShowFrame,
+        ];
+        let mut data = create_test_swf(&tags);
+        let metadata = read_metadata("test.swf", &data[..], &options).unwrap();
+        assert!(metadata.header_anomalies.is_empty());
+
+        // Short FileAttributes header (code 69, length 4) followed by the flags
+        let start = data.windows(2).position(|w| w == [0x44, 0x11]).unwrap() + 2;
+        data[start] |= 0x80;
+        data[start + 2] = 0x01;
+        let metadata = read_metadata("test.swf", &data[..], &options).unwrap();
+        assert_eq!(
+            metadata.header_anomalies,
+            vec!["FileAttributes reserved bits set: 0x00010080".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_parse_warnings_omitted_by_default() {
+        let metadata = metadata_from_tags(&[swf::Tag::ShowFrame]);
+        assert_eq!(metadata.parse_warnings, None);
+        assert!(!format(&metadata, Format::Json).contains("parse_warnings"));
+    }
+
+    #[test]
+    fn test_broken_import_flagged() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = create_test_swf(&[
+            swf::Tag::ExportAssets(vec![swf::ExportedAsset {
+                id: 1,
+                name: swf::SwfStr::from_utf8_str("Present"),
+            }]),
+            swf::Tag::ShowFrame,
+        ]);
+        std::fs::write(dir.path().join("library.swf"), library).unwrap();
+        let main = create_test_swf(&[
+            swf::Tag::ImportAssets {
+                url: swf::SwfStr::from_utf8_str("library.swf"),
+                imports: vec![
+                    swf::ExportedAsset {
+                        id: 1,
+                        name: swf::SwfStr::from_utf8_str("Present"),
+                    },
+                    swf::ExportedAsset {
+                        id: 2,
+                        name: swf::SwfStr::from_utf8_str("Missing"),
+                    },
+                ],
+            },
+            swf::Tag::ShowFrame,
+        ]);
+        let main_path = dir.path().join("main.swf");
+        std::fs::write(&main_path, &main).unwrap();
+
+        let options = ExtractOptions {
+            dereference_imports: true,
+            ..Default::default()
+        };
+        let metadata = read_metadata(main_path.to_str().unwrap(), &main[..], &options).unwrap();
+        assert_eq!(
+            metadata.broken_imports,
+            Some(vec!["library.swf:Missing".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_displaylist_depth_stats() {
+        let options = ExtractOptions {
+            displaylist: true,
+            ..Default::default()
+        };
+        let metadata = metadata_from_tags_with(
+            &[
+                place_object(1, 1),
+                place_object(2, 5),
+                place_object(3, 3),
+                swf::Tag::ShowFrame,
+                place_object(1, 5),
+                swf::Tag::ShowFrame,
+            ],
+            &options,
+        );
+        assert_eq!(metadata.max_depth_used, Some(5));
+        assert_eq!(metadata.distinct_depths, Some(3));
+    }
+
+    #[test]
+    fn test_blend_modes() {
+        let options = ExtractOptions {
+            displaylist: true,
+            ..Default::default()
+        };
+        let multiply = swf::PlaceObject {
+            version: 3,
+            blend_mode: Some(swf::BlendMode::Multiply),
+            ..placement(1, 1)
+        };
+        let metadata = metadata_from_tags_with(
+            &[
+                swf::Tag::PlaceObject(Box::new(multiply)),
+                place_object(2, 2),
+                swf::Tag::ShowFrame,
+            ],
+            &options,
+        );
+        assert_eq!(metadata.blend_modes, Some(vec!["multiply".to_string()]));
+    }
+
+    #[test]
+    fn test_cache_as_bitmap_count() {
+        let options = ExtractOptions {
+            displaylist: true,
+            ..Default::default()
+        };
+        let cached = || {
+            swf::Tag::PlaceObject(Box::new(swf::PlaceObject {
+                version: 3,
+                is_bitmap_cached: Some(true),
+                ..placement(1, 1)
+            }))
+        };
+        let metadata = metadata_from_tags_with(
+            &[
+                cached(),
+                place_object(2, 2),
+                swf::Tag::DefineSprite(swf::Sprite {
+                    id: 3,
+                    num_frames: 1,
+                    tags: vec![cached(), swf::Tag::ShowFrame],
+                }),
+                swf::Tag::ShowFrame,
+            ],
+            &options,
+        );
+        assert_eq!(metadata.cache_as_bitmap_count, Some(2));
+    }
+
+    #[test]
+    fn test_cff_fonts_reported() {
+        let options = ExtractOptions {
+            fonts: true,
+            ..Default::default()
+        };
+        let metadata = metadata_from_tags_with(
+            &[
+                swf::Tag::DefineFont4(swf::Font4 {
+                    id: 1,
+                    is_italic: false,
+                    is_bold: false,
+                    name: swf::SwfStr::from_utf8_str("Embedded Sans"),
+                    data: Some(&[0x4f, 0x54, 0x54, 0x4f]),
+                }),
+                swf::Tag::DefineFont4(swf::Font4 {
+                    id: 2,
+                    is_italic: false,
+                    is_bold: false,
+                    name: swf::SwfStr::from_utf8_str("Device Font"),
+                    data: None,
+                }),
+                swf::Tag::ShowFrame,
+            ],
+            &options,
+        );
+        assert_eq!(metadata.has_cff_fonts, Some(true));
+        assert_eq!(
+            metadata.cff_font_names,
+            Some(vec!["Embedded Sans".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_total_glyph_count() {
+        let options = ExtractOptions {
+            fonts: true,
+            ..Default::default()
+        };
+        let glyph = |code| swf::Glyph {
+            shape_records: Vec::new(),
+            code,
+            advance: 0,
+            bounds: None,
+        };
+        let metadata = metadata_from_tags_with(
+            &[
+                swf::Tag::DefineFont(Box::new(swf::FontV1 {
+                    id: 1,
+                    glyphs: vec![Vec::new(); 2],
+                })),
+                swf::Tag::DefineFont2(Box::new(swf::Font {
+                    version: 3,
+                    id: 2,
+                    name: swf::SwfStr::from_utf8_str("Full Sans"),
+                    language: swf::Language::Unknown,
+                    layout: None,
+                    glyphs: (0..95).map(|code| glyph(32 + code)).collect(),
+                    flags: swf::FontFlag::empty(),
+                })),
+                swf::Tag::ShowFrame,
+            ],
+            &options,
+        );
+        assert_eq!(metadata.total_glyph_count, Some(97));
+        assert_eq!(
+            metadata_from_tags(&[swf::Tag::ShowFrame]).total_glyph_count,
+            None
+        );
+    }
+
+    #[test]
+    fn test_font_copyrights() {
+        let options = ExtractOptions {
+            fonts: true,
+            ..Default::default()
+        };
+        let metadata = metadata_from_tags_with(
+            &[
+                swf::Tag::DefineFontName {
+                    id: 1,
+                    name: swf::SwfStr::from_utf8_str("Embedded Sans"),
+                    copyright_info: swf::SwfStr::from_utf8_str("(c) 2004 Example Foundry"),
+                },
+                swf::Tag::ShowFrame,
+            ],
+            &options,
+        );
+        assert_eq!(
+            metadata.font_copyrights,
+            Some(vec!["(c) 2004 Example Foundry".to_string()])
+        );
+        assert_eq!(
+            metadata_from_tags(&[swf::Tag::ShowFrame]).font_copyrights,
+            None
+        );
+    }
+
+    // Helper function to encode AVM1 actions into DoAction bytecode
+    fn avm1_actions(actions: &[swf::avm1::types::Action]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut writer = swf::avm1::write::Writer::new(&mut data, 10);
+        for action in actions {
+            writer.write_action(action).unwrap();
+        }
+        data
+    }
+
+    // Helper function to create an ABC file with a single method body running `ops`,
+    // where multiname `n` is a public QName for `names[n - 1]`
+    fn abc_file(names: &[&str], ops: &[swf::avm2::types::Op]) -> Vec<u8> {
+        use swf::avm2::types::*;
+
+        let mut code = Vec::new();
+        let mut writer = swf::avm2::write::Writer::new(&mut code);
+        for op in ops {
+            writer.write_op(op).unwrap();
+        }
+        let mut strings: Vec<Vec<u8>> = names.iter().map(|name| name.as_bytes().to_vec()).collect();
+        strings.push(Vec::new());
+        let abc = AbcFile {
+            major_version: 46,
+            minor_version: 16,
+            constant_pool: ConstantPool {
+                ints: vec![],
+                uints: vec![],
+                doubles: vec![],
+                strings,
+                namespaces: vec![Namespace::Package(Index::new(names.len() as u32 + 1))],
+                namespace_sets: vec![],
+                multinames: (1..=names.len() as u32)
+                    .map(|name| Multiname::QName {
+                        namespace: Index::new(1),
+                        name: Index::new(name),
+                    })
+                    .collect(),
+            },
+            methods: vec![Method {
+                name: Index::new(0),
+                params: vec![],
+                return_type: Index::new(0),
+                flags: MethodFlags::empty(),
+                body: None,
+            }],
+            metadata: vec![],
+            instances: vec![],
+            classes: vec![],
+            scripts: vec![Script {
+                init_method: Index::new(0),
+                traits: vec![],
+            }],
+            method_bodies: vec![MethodBody {
+                method: Index::new(0),
+                max_stack: 2,
+                num_locals: 1,
+                init_scope_depth: 0,
+                max_scope_depth: 1,
+                code,
+                exceptions: vec![],
+                traits: vec![],
+            }],
+        };
+        let mut data = Vec::new();
+        swf::avm2::write::Writer::new(&mut data).write(abc).unwrap();
+        data
+    }
+
+    // Helper function to create a lossless bitmap tag of the given size
+    fn lossless_bitmap(id: u16, width: u16, height: u16) -> swf::Tag<'static> {
+        swf::Tag::DefineBitsLossless(swf::DefineBitsLossless {
+            version: 2,
+            id,
+            format: swf::BitmapFormat::Rgb32,
+            width,
+            height,
+            data: std::borrow::Cow::Borrowed(&[0x78, 0x9c]),
+        })
+    }
+
+    #[test]
+    fn test_bitmap_formats_sniff_payloads() {
+        let options = ExtractOptions {
+            bitmaps: true,
+            ..Default::default()
+        };
+        let metadata = metadata_from_tags_with(
+            &[
+                swf::Tag::DefineBitsJpeg2 {
+                    id: 1,
+                    jpeg_data: b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR",
+                },
+                swf::Tag::DefineBitsJpeg2 {
+                    id: 2,
+                    jpeg_data: &[0xFF, 0xD8, 0xFF, 0xD9],
+                },
+                lossless_bitmap(3, 1, 1),
+            ],
+            &options,
+        );
+        let expected = BTreeMap::from([("jpeg".to_string(), 1), ("png".to_string(), 1)]);
+        assert_eq!(metadata.bitmap_formats, Some(expected));
+    }
+
+    #[test]
+    fn test_estimated_bitmap_memory() {
+        let options = ExtractOptions {
+            bitmaps: true,
+            ..Default::default()
+        };
+        let metadata = metadata_from_tags_with(
+            &[
+                lossless_bitmap(1, 10, 20),
+                lossless_bitmap(2, 30, 40),
+                swf::Tag::ShowFrame,
+            ],
+            &options,
+        );
+        assert_eq!(
+            metadata.estimated_bitmap_memory_bytes,
+            Some((10 * 20 + 30 * 40) * 4)
+        );
+    }
+
+    #[test]
+    fn test_jpeg_dimensions_skip_segments() {
+        // Bogus EOI+SOI prefix, an APP0 segment, then a baseline SOF0 for 64x32
+        let jpeg = [
+            0xFF, 0xD9, 0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xC0, 0x00, 0x11,
+            0x08, 0x00, 0x20, 0x00, 0x40, 0x03,
+        ];
+        assert_eq!(image_dimensions(&jpeg), Some((64, 32)));
+    }
+
+    #[test]
+    fn test_empty_sprite_count() {
+        let options = ExtractOptions {
+            dead_code: true,
+            ..Default::default()
+        };
+        let metadata = metadata_from_tags_with(
+            &[
+                swf::Tag::DefineSprite(swf::Sprite {
+                    id: 1,
+                    num_frames: 0,
+                    tags: vec![],
+                }),
+                swf::Tag::DefineSprite(swf::Sprite {
+                    id: 2,
+                    num_frames: 1,
+                    tags: vec![place_object(1, 1), swf::Tag::ShowFrame],
+                }),
+                swf::Tag::ShowFrame,
+            ],
+            &options,
+        );
+        assert_eq!(metadata.empty_sprite_count, Some(1));
+    }
+
+    #[test]
+    fn test_init_action_sprite_ids() {
+        let options = ExtractOptions {
+            scripts: true,
+            ..Default::default()
+        };
+        let metadata = metadata_from_tags_with(
+            &[
+                swf::Tag::DefineSprite(swf::Sprite {
+                    id: 7,
+                    num_frames: 1,
+                    tags: vec![swf::Tag::ShowFrame],
+                }),
+                swf::Tag::DoInitAction {
+                    id: 7,
+                    action_data: &[0x00],
+                },
+                swf::Tag::ShowFrame,
+            ],
+            &options,
+        );
+        assert_eq!(metadata.init_actions, Some(vec![7]));
+    }
+
+    #[test]
+    fn test_trim_prefix_shortens_file_names() {
+        let prefix = Path::new("/archive/flash/2004");
+        assert_eq!(
+            trim_file_name("/archive/flash/2004/intro/intro.swf", prefix),
+            "intro/intro.swf"
+        );
+        assert_eq!(
+            trim_file_name("/archive/flash/2004/menu.swf", prefix),
+            "menu.swf"
+        );
+        // Only whole path components are stripped
+        assert_eq!(
+            trim_file_name("/archive/flash/20041/menu.swf", prefix),
+            "/archive/flash/20041/menu.swf"
+        );
+    }
+
+    #[test]
+    fn test_trim_prefix_applied_to_metadata() {
+        let data = create_test_swf(&[swf::Tag::ShowFrame]);
+        let options = ExtractOptions {
+            trim_prefix: Some(PathBuf::from("assets")),
+            ..Default::default()
+        };
+        let metadata = read_metadata("assets/ui/button.swf", &data[..], &options).unwrap();
+        assert_eq!(metadata.file_name, "ui/button.swf");
+    }
+
+    // Helper function to create a shape tag with the given bounds in pixels
+    fn define_shape(id: u16, width: f64, height: f64) -> swf::Tag<'static> {
+        let bounds = swf::Rectangle {
+            x_min: swf::Twips::ZERO,
+            x_max: swf::Twips::from_pixels(width),
+            y_min: swf::Twips::ZERO,
+            y_max: swf::Twips::from_pixels(height),
+        };
+        swf::Tag::DefineShape(swf::Shape {
+            version: 1,
+            id,
+            shape_bounds: bounds.clone(),
+            edge_bounds: bounds,
+            flags: swf::ShapeFlag::empty(),
+            styles: swf::ShapeStyles {
+                fill_styles: vec![],
+                line_styles: vec![],
+            },
+            shape: vec![],
+        })
+    }
+
+    #[test]
+    fn test_nonzero_winding() {
+        let options = ExtractOptions {
+            shapes: true,
+            ..Default::default()
+        };
+        let metadata = metadata_from_tags_with(&[define_shape(1, 10.0, 10.0)], &options);
+        assert_eq!(metadata.uses_nonzero_winding, Some(false));
+
+        let swf::Tag::DefineShape(mut shape) = define_shape(1, 10.0, 10.0) else {
+            unreachable!()
+        };
+        shape.version = 4;
+        shape.flags = swf::ShapeFlag::NON_ZERO_WINDING_RULE;
+        let metadata = metadata_from_tags_with(&[swf::Tag::DefineShape(shape)], &options);
+        assert_eq!(metadata.uses_nonzero_winding, Some(true));
+    }
+
+    #[test]
+    fn test_offstage_content_detected() {
+        let options = ExtractOptions {
+            shapes: true,
+            ..Default::default()
+        };
+        let mut offstage = placement(1, 1);
+        offstage.matrix = Some(swf::Matrix::translate(
+            swf::Twips::from_pixels(750.0),
+            swf::Twips::from_pixels(10.0),
+        ));
+        let metadata = metadata_from_tags_with(
+            &[
+                define_shape(1, 100.0, 100.0),
+                swf::Tag::PlaceObject(Box::new(offstage)),
+                swf::Tag::ShowFrame,
+            ],
+            &options,
+        );
+        assert_eq!(metadata.has_offstage_content, Some(true));
+    }
+
+    #[test]
+    fn test_onstage_content_not_flagged() {
+        let options = ExtractOptions {
+            shapes: true,
+            ..Default::default()
+        };
+        let metadata = metadata_from_tags_with(
+            &[
+                define_shape(1, 100.0, 100.0),
+                place_object(1, 1),
+                swf::Tag::ShowFrame,
+            ],
+            &options,
+        );
+        assert_eq!(metadata.has_offstage_content, Some(false));
+    }
+
+    #[test]
+    fn test_morph_tween_count() {
+        let options = ExtractOptions {
+            shapes: true,
+            ..Default::default()
+        };
+        let empty_morph = swf::MorphShape {
+            shape_bounds: swf::Rectangle::default(),
+            edge_bounds: swf::Rectangle::default(),
+            fill_styles: vec![],
+            line_styles: vec![],
+            shape: vec![],
+        };
+        let morph = swf::Tag::DefineMorphShape(Box::new(swf::DefineMorphShape {
+            version: 1,
+            id: 2,
+            flags: swf::DefineMorphShapeFlag::empty(),
+            start: empty_morph.clone(),
+            end: empty_morph,
+        }));
+        let tween_start = swf::PlaceObject {
+            ratio: Some(0),
+            ..placement(2, 1)
+        };
+        let tween_end = swf::PlaceObject {
+            action: swf::PlaceObjectAction::Modify,
+            ratio: Some(65535),
+            ..placement(2, 1)
+        };
+        // A ratio on a plain shape is not a morph tween
+        let plain_ratio = swf::PlaceObject {
+            ratio: Some(100),
+            ..placement(1, 2)
+        };
+        let metadata = metadata_from_tags_with(
+            &[
+                define_shape(1, 10.0, 10.0),
+                morph,
+                swf::Tag::PlaceObject(Box::new(tween_start)),
+                swf::Tag::PlaceObject(Box::new(plain_ratio)),
+                swf::Tag::ShowFrame,
+                swf::Tag::PlaceObject(Box::new(tween_end)),
+                swf::Tag::ShowFrame,
+            ],
+            &options,
+        );
+        assert_eq!(metadata.morph_tween_count, Some(2));
+    }
+
+    #[test]
+    fn test_has_looping_sound() {
+        let options = ExtractOptions {
+            audio: true,
+            ..Default::default()
+        };
+        let start_sound = |num_loops| {
+            swf::Tag::StartSound(swf::StartSound {
+                id: 1,
+                sound_info: Box::new(swf::SoundInfo {
+                    event: swf::SoundEvent::Start,
+                    in_sample: None,
+                    out_sample: None,
+                    num_loops,
+                    envelope: None,
+                }),
+            })
+        };
+        let sound = || {
+            swf::Tag::DefineSound(Box::new(swf::Sound {
+                id: 1,
+                format: swf::SoundFormat {
+                    compression: swf::AudioCompression::Uncompressed,
+                    sample_rate: 44100,
+                    is_stereo: false,
+                    is_16_bit: false,
+                },
+                num_samples: 4,
+                data: &[0; 4],
+            }))
+        };
+        let metadata =
+            metadata_from_tags_with(&[sound(), start_sound(999), swf::Tag::ShowFrame], &options);
+        assert_eq!(metadata.has_looping_sound, Some(true));
+
+        let metadata =
+            metadata_from_tags_with(&[sound(), start_sound(1), swf::Tag::ShowFrame], &options);
+        assert_eq!(metadata.has_looping_sound, Some(false));
+    }
+
+    #[test]
+    fn test_sound_stream_block_frames() {
+        let options = ExtractOptions {
+            audio: true,
+            ..Default::default()
+        };
+        let metadata = metadata_from_tags_with(
+            &[
+                swf::Tag::ShowFrame,
+                swf::Tag::SoundStreamBlock(&[0x01, 0x02]),
+                swf::Tag::ShowFrame,
+                swf::Tag::ShowFrame,
+            ],
+            &options,
+        );
+        assert_eq!(metadata.sound_frames, Some(vec![1]));
+    }
+
+    #[test]
+    fn test_usage_ratio_counts_placed_characters() {
+        let options = ExtractOptions {
+            dead_code: true,
+            ..Default::default()
+        };
+        let metadata = metadata_from_tags_with(
+            &[
+                define_shape(1, 10.0, 10.0),
+                define_shape(2, 10.0, 10.0),
+                define_shape(3, 10.0, 10.0),
+                define_shape(4, 10.0, 10.0),
+                place_object(1, 1),
+                place_object(3, 2),
+                swf::Tag::ShowFrame,
+            ],
+            &options,
+        );
+        assert_eq!(metadata.usage_ratio, Some(0.5));
+    }
+
+    #[test]
+    fn test_reused_sprite_count() {
+        let options = ExtractOptions {
+            dead_code: true,
+            ..Default::default()
+        };
+        let sprite = |id| {
+            swf::Tag::DefineSprite(swf::Sprite {
+                id,
+                num_frames: 1,
+                tags: vec![swf::Tag::ShowFrame],
+            })
+        };
+        let metadata = metadata_from_tags_with(
+            &[
+                sprite(1),
+                sprite(2),
+                define_shape(3, 10.0, 10.0),
+                place_object(1, 1),
+                place_object(1, 2),
+                place_object(2, 3),
+                // Shapes placed twice are not components
+                place_object(3, 4),
+                place_object(3, 5),
+                swf::Tag::ShowFrame,
+            ],
+            &options,
+        );
+        assert_eq!(metadata.reused_sprite_count, Some(1));
+    }
+
+    #[test]
+    fn test_avm1_stage_scale_mode() {
+        use swf::avm1::types::{Action, Push, Value};
+
+        let options = ExtractOptions {
+            scripts: true,
+            ..Default::default()
+        };
+        // Stage.scaleMode = "noScale";
+        let actions = avm1_actions(&[
+            Action::Push(Push {
+                values: vec![Value::Str("Stage".into())],
+            }),
+            Action::GetVariable,
+            Action::Push(Push {
+                values: vec![Value::Str("scaleMode".into()), Value::Str("noScale".into())],
+            }),
+            Action::SetMember,
+            Action::End,
+        ]);
+        let metadata = metadata_from_tags_with(
+            &[swf::Tag::DoAction(&actions), swf::Tag::ShowFrame],
+            &options,
+        );
+        assert_eq!(metadata.scale_mode, Some("noScale".to_string()));
+        assert_eq!(metadata.stage_align, None);
+    }
+
+    #[test]
+    fn test_scripted_frame_rates() {
+        use swf::avm1::types::{Action, Push, Value};
+
+        let options = ExtractOptions {
+            scripts: true,
+            ..Default::default()
+        };
+        // Stage.frameRate = rate;
+        let set_frame_rate = |rate: Value<'static>| {
+            vec![
+                Action::Push(Push {
+                    values: vec![Value::Str("Stage".into())],
+                }),
+                Action::GetVariable,
+                Action::Push(Push {
+                    values: vec![Value::Str("frameRate".into()), rate],
+                }),
+                Action::SetMember,
+            ]
+        };
+        let mut actions = set_frame_rate(Value::Int(12));
+        actions.extend(set_frame_rate(Value::Double(29.97)));
+        actions.extend(set_frame_rate(Value::Int(12)));
+        actions.push(Action::End);
+        let actions = avm1_actions(&actions);
+        let metadata = metadata_from_tags_with(
+            &[swf::Tag::DoAction(&actions), swf::Tag::ShowFrame],
+            &options,
+        );
+        assert_eq!(metadata.scripted_frame_rates, Some(vec![12.0, 29.97]));
+    }
+
+    #[test]
+    fn test_preloader_stub_detected() {
+        use swf::avm1::types::{Action, GetUrl};
+
+        let options = ExtractOptions {
+            scripts: true,
+            ..Default::default()
+        };
+        // loadMovieNum("main.swf?v=2", 0);
+        let load_main = avm1_actions(&[
+            Action::GetUrl(GetUrl {
+                url: "main.swf?v=2".into(),
+                target: "_level0".into(),
+            }),
+            Action::End,
+        ]);
+        let tags = [swf::Tag::DoAction(&load_main), swf::Tag::ShowFrame];
+        let header = swf::Header {
+            stage_size: swf::Rectangle {
+                x_min: swf::Twips::ZERO,
+                x_max: swf::Twips::from_pixels(550.0),
+                y_min: swf::Twips::ZERO,
+                y_max: swf::Twips::from_pixels(400.0),
+            },
+            num_frames: 1,
+            ..create_test_header()
+        };
+        let data = create_test_swf_with_header(&header, &tags);
+        let metadata = read_metadata("test.swf", &data[..], &options).unwrap();
+        assert_eq!(metadata.is_preloader, Some(true));
+
+        // The same script in a 100-frame, 800x600 movie is real content
+        let metadata = metadata_from_tags_with(&tags, &options);
+        assert_eq!(metadata.is_preloader, Some(false));
+    }
+
+    #[test]
+    fn test_mixed_avm() {
+        use swf::avm2::types::Op;
+
+        // Reported without --scripts
+        let actions = avm1_actions(&[swf::avm1::types::Action::End]);
+        let abc = abc_file(&[], &[Op::ReturnVoid]);
+        let metadata = metadata_from_tags(&[
+            swf::Tag::DoAbc(&abc),
+            swf::Tag::DoAction(&actions),
+            swf::Tag::ShowFrame,
+        ]);
+        assert!(metadata.mixed_avm);
+
+        let metadata = metadata_from_tags(&[swf::Tag::DoAbc(&abc), swf::Tag::ShowFrame]);
+        assert!(!metadata.mixed_avm);
+    }
+
+    #[test]
+    fn test_custom_cursor() {
+        use swf::avm1::types::{Action, Push, Value};
+
+        let options = ExtractOptions {
+            scripts: true,
+            ..Default::default()
+        };
+        // Mouse.hide(); startDrag("cursor", true);
+        let mut actions = vec![
+            Action::Push(Push {
+                values: vec![
+                    Value::Int(0),
+                    Value::Str("Mouse".into()),
+                    Value::Str("hide".into()),
+                ],
+            }),
+            Action::CallMethod,
+            Action::Pop,
+            Action::Push(Push {
+                values: vec![
+                    Value::Str("cursor".into()),
+                    Value::Bool(true),
+                    Value::Int(0),
+                ],
+            }),
+            Action::StartDrag,
+            Action::End,
+        ];
+        let metadata =
+            metadata_from_tags_with(&[swf::Tag::DoAction(&avm1_actions(&actions))], &options);
+        assert_eq!(metadata.has_custom_cursor, Some(true));
+
+        // Dragging a clip without hiding the pointer is an ordinary drag
+        actions.drain(..3);
+        let metadata =
+            metadata_from_tags_with(&[swf::Tag::DoAction(&avm1_actions(&actions))], &options);
+        assert_eq!(metadata.has_custom_cursor, Some(false));
+    }
+
+    #[test]
+    fn test_clip_events() {
+        let options = ExtractOptions {
+            scripts: true,
+            ..Default::default()
+        };
+        let stop = avm1_actions(&[
+            swf::avm1::types::Action::Stop,
+            swf::avm1::types::Action::End,
+        ]);
+        // on (press) { stop(); }
+        let clip = swf::PlaceObject {
+            clip_actions: Some(vec![swf::ClipAction {
+                events: swf::ClipEventFlag::PRESS,
+                key_code: None,
+                action_data: &stop,
+            }]),
+            ..placement(1, 1)
+        };
+        let metadata = metadata_from_tags_with(
+            &[
+                swf::Tag::DefineSprite(swf::Sprite {
+                    id: 1,
+                    num_frames: 1,
+                    tags: vec![swf::Tag::ShowFrame],
+                }),
+                swf::Tag::PlaceObject(Box::new(clip)),
+                swf::Tag::ShowFrame,
+            ],
+            &options,
+        );
+        assert_eq!(metadata.clip_event_count, Some(1));
+        assert_eq!(metadata.clip_event_types, Some(vec!["onPress".to_string()]));
+    }
+
+    #[test]
+    fn test_avm2_stage_align_constant() {
+        use swf::avm2::types::{Index, Op};
+
+        let options = ExtractOptions {
+            scripts: true,
+            ..Default::default()
+        };
+        // stage.align = StageAlign.TOP_LEFT;
+        let abc = abc_file(
+            &["stage", "align", "StageAlign", "TOP_LEFT"],
+            &[
+                Op::GetLocal { index: 0 },
+                Op::GetProperty {
+                    index: Index::new(1),
+                },
+                Op::GetLex {
+                    index: Index::new(3),
+                },
+                Op::GetProperty {
+                    index: Index::new(4),
+                },
+                Op::SetProperty {
+                    index: Index::new(2),
+                },
+                Op::ReturnVoid,
+            ],
+        );
+        let metadata =
+            metadata_from_tags_with(&[swf::Tag::DoAbc(&abc), swf::Tag::ShowFrame], &options);
+        assert_eq!(metadata.stage_align, Some("TL".to_string()));
+    }
+
+    #[test]
+    fn test_empty_video_streams() {
+        let options = ExtractOptions {
+            video: true,
+            ..Default::default()
+        };
+        let stream = |id| {
+            swf::Tag::DefineVideoStream(swf::DefineVideoStream {
+                id,
+                num_frames: 1,
+                width: 320,
+                height: 240,
+                is_smoothed: false,
+                deblocking: swf::VideoDeblocking::UseVideoPacketValue,
+                codec: swf::VideoCodec::H263,
+            })
+        };
+        let metadata = metadata_from_tags_with(
+            &[
+                stream(1),
+                stream(2),
+                swf::Tag::VideoFrame(swf::VideoFrame {
+                    stream_id: 1,
+                    frame_num: 0,
+                    data: &[0x00, 0x00, 0x84],
+                }),
+                swf::Tag::ShowFrame,
+            ],
+            &options,
+        );
+        assert_eq!(metadata.empty_video_streams, Some(vec![2]));
+    }
+
+    #[test]
+    fn test_binary_data_count_and_size() {
+        let options = ExtractOptions {
+            binary_data: true,
+            ..Default::default()
+        };
+        let metadata = metadata_from_tags_with(
+            &[
+                swf::Tag::DefineBinaryData(swf::DefineBinaryData {
+                    id: 1,
+                    data: &[0u8; 100],
+                }),
+                swf::Tag::DefineBinaryData(swf::DefineBinaryData {
+                    id: 2,
+                    data: &[0u8; 28],
+                }),
+                swf::Tag::ShowFrame,
+            ],
+            &options,
+        );
+        assert_eq!(metadata.binary_blob_count, Some(2));
+        assert_eq!(metadata.binary_total_bytes, Some(128));
+    }
+
+    #[test]
+    fn test_interactive_buttons() {
+        use swf::avm1::types::{Action, GetUrl};
+
+        let options = ExtractOptions {
+            buttons: true,
+            ..Default::default()
+        };
+        let on_release = avm1_actions(&[
+            Action::GetUrl(GetUrl {
+                url: "http://example.com/".into(),
+                target: "_blank".into(),
+            }),
+            Action::End,
+        ]);
+        let button = |id, action_data| {
+            swf::Tag::DefineButton2(Box::new(swf::Button {
+                id,
+                is_track_as_menu: false,
+                records: vec![],
+                actions: vec![swf::ButtonAction {
+                    conditions: swf::ButtonActionCondition::OVER_DOWN_TO_OVER_UP,
+                    action_data,
+                }],
+            }))
+        };
+        let metadata = metadata_from_tags_with(
+            &[button(1, &on_release), button(2, &[0]), swf::Tag::ShowFrame],
+            &options,
+        );
+        assert_eq!(metadata.interactive_button_count, Some(1));
+        assert_eq!(
+            metadata.button_urls,
+            Some(vec!["http://example.com/".to_string()])
+        );
+        assert_eq!(metadata.filtered_button_count, Some(0));
+    }
+
+    #[test]
+    fn test_filtered_buttons() {
+        let options = ExtractOptions {
+            buttons: true,
+            ..Default::default()
+        };
+        let button = |id, filters| {
+            swf::Tag::DefineButton2(Box::new(swf::Button {
+                id,
+                is_track_as_menu: false,
+                records: vec![swf::ButtonRecord {
+                    states: swf::ButtonState::UP,
+                    id: 10,
+                    depth: 1,
+                    matrix: swf::Matrix::IDENTITY,
+                    color_transform: swf::ColorTransform::IDENTITY,
+                    filters,
+                    blend_mode: swf::BlendMode::Normal,
+                }],
+                actions: vec![],
+            }))
+        };
+        let blur = swf::Filter::BlurFilter(Box::new(swf::BlurFilter {
+            blur_x: swf::Fixed16::from_f32(4.0),
+            blur_y: swf::Fixed16::from_f32(4.0),
+            flags: swf::BlurFilterFlags::from_passes(1),
+        }));
+        let metadata = metadata_from_tags_with(
+            &[
+                button(1, vec![blur]),
+                button(2, vec![]),
+                swf::Tag::ShowFrame,
+            ],
+            &options,
+        );
+        assert_eq!(metadata.filtered_button_count, Some(1));
+    }
+
+    #[test]
+    fn test_rsl_usage_detected() {
+        use swf::avm2::types::Op;
+
+        let options = ExtractOptions {
+            classes: true,
+            ..Default::default()
+        };
+        // The strings Flex's SystemManager.info() carries for a framework RSL
+        let abc = abc_file(
+            &[
+                "cdRsls",
+                "rsls",
+                "framework_4.6.0.23201.swz",
+                "framework_4.6.0.23201.swf",
+            ],
+            &[Op::ReturnVoid],
+        );
+        let metadata =
+            metadata_from_tags_with(&[swf::Tag::DoAbc(&abc), swf::Tag::ShowFrame], &options);
+        assert_eq!(metadata.uses_rsl, Some(true));
+        assert_eq!(
+            metadata.rsl_libraries,
+            Some(vec![
+                "framework_4.6.0.23201.swf".to_string(),
+                "framework_4.6.0.23201.swz".to_string(),
+            ])
+        );
+
+        let metadata = metadata_from_tags_with(&[swf::Tag::ShowFrame], &options);
+        assert_eq!(metadata.uses_rsl, Some(false));
+    }
+
+    #[test]
+    fn test_long_tag_count() {
+        let options = ExtractOptions {
+            tags: true,
+            ..Default::default()
+        };
+        let metadata = metadata_from_tags_with(
+            &[
+                swf::Tag::DefineBinaryData(swf::DefineBinaryData {
+                    id: 1,
+                    data: &[0u8; 200],
+                }),
+                swf::Tag::ShowFrame,
+            ],
+            &options,
+        );
+        assert_eq!(metadata.long_tag_count, Some(1));
+    }
+
+    #[test]
+    fn test_tags_after_last_frame() {
+        let options = ExtractOptions {
+            tags: true,
+            ..Default::default()
+        };
+        let cleanup = avm1_actions(&[
+            swf::avm1::types::Action::Stop,
+            swf::avm1::types::Action::End,
+        ]);
+        let metadata = metadata_from_tags_with(
+            &[
+                swf::Tag::ShowFrame,
+                swf::Tag::DoAction(&cleanup),
+                swf::Tag::ShowFrame,
+                swf::Tag::DoAction(&cleanup),
+            ],
+            &options,
+        );
+        assert_eq!(metadata.tags_after_last_frame, Some(1));
+
+        let metadata = metadata_from_tags_with(&[swf::Tag::ShowFrame], &options);
+        assert_eq!(metadata.tags_after_last_frame, Some(0));
+    }
+
+    #[test]
+    fn test_tag_size_histogram() {
+        let options = ExtractOptions {
+            tags: true,
+            ..Default::default()
+        };
+        // DefineBinaryData payloads are the data plus a 2-byte id and 4 reserved bytes
+        let metadata = metadata_from_tags_with(
+            &[
+                swf::Tag::DefineBinaryData(swf::DefineBinaryData {
+                    id: 1,
+                    data: &[0u8; 100],
+                }),
+                swf::Tag::DefineBinaryData(swf::DefineBinaryData {
+                    id: 2,
+                    data: &[0u8; 2000],
+                }),
+                swf::Tag::DefineBinaryData(swf::DefineBinaryData {
+                    id: 3,
+                    data: &[0u8; 70000],
+                }),
+                swf::Tag::ShowFrame,
+            ],
+            &options,
+        );
+        let histogram = metadata.tag_size_histogram.unwrap();
+        // ShowFrame and the End tag written after it are empty
+        assert_eq!(histogram["0-63"], 2);
+        assert_eq!(histogram["64-255"], 1);
+        assert_eq!(histogram["256-1K"], 0);
+        assert_eq!(histogram["1K-64K"], 1);
+        assert_eq!(histogram["64K+"], 1);
+    }
+
+    #[test]
+    fn test_tag_counts() {
+        let options = ExtractOptions {
+            tag_counts: true,
+            ..Default::default()
+        };
+        let metadata = metadata_from_tags_with(
+            &[
+                swf::Tag::ShowFrame,
+                swf::Tag::DefineSprite(swf::Sprite {
+                    id: 1,
+                    num_frames: 1,
+                    tags: vec![swf::Tag::ShowFrame],
+                }),
+                swf::Tag::ShowFrame,
+            ],
+            &options,
+        );
+        let counts = metadata.tag_counts.unwrap();
+        // The sprite's own ShowFrame and End tags are counted alongside the root's
+        assert_eq!(counts["ShowFrame"], 3);
+        assert_eq!(counts["DefineSprite"], 1);
+        assert_eq!(counts["End"], 2);
+        assert!(metadata.tag_size_histogram.is_none());
+        assert!(metadata.tag_descriptions.is_none());
+    }
+
+    #[test]
+    fn test_tag_descriptions() {
+        let options = ExtractOptions {
+            tag_counts: true,
+            describe_tags: true,
+            ..Default::default()
+        };
+        let metadata = metadata_from_tags_with(&[swf::Tag::ShowFrame], &options);
+        let descriptions = metadata.tag_descriptions.unwrap();
+        assert_eq!(descriptions["ShowFrame"], "displays the current frame");
+        // Keyed exactly like the counts they describe
+        assert!(descriptions.keys().eq(metadata.tag_counts.unwrap().keys()));
+        assert_eq!(
+            tag_description(swf::TagCode::DefineShape4),
+            "vector shape with extended fills and line styles"
+        );
+    }
+
+    #[test]
+    fn test_min_version_by_tags() {
+        let options = ExtractOptions {
+            tags: true,
+            ..Default::default()
+        };
+        let header = swf::Header {
+            version: 6,
+            ..create_test_header()
+        };
+        // DefineScalingGrid was introduced in SWF 8
+        let data = create_test_swf_with_header(
+            &header,
+            &[
+                define_shape(1, 10.0, 10.0),
+                swf::Tag::DefineScalingGrid {
+                    id: 1,
+                    splitter_rect: swf::Rectangle::default(),
+                },
+                swf::Tag::ShowFrame,
+            ],
+        );
+        let metadata = read_metadata("test.swf", &data[..], &options).unwrap();
+        assert_eq!(metadata.min_version_by_tags, Some(8));
+    }
+
+    #[test]
+    fn test_short_bitmap_header_warning() {
+        install_warning_collector();
+        let options = ExtractOptions {
+            tags: true,
+            warnings: true,
+            ..Default::default()
+        };
+        let metadata = metadata_from_tags_with(&[lossless_bitmap(1, 1, 1)], &options);
+        assert_eq!(metadata.long_tag_count, Some(0));
+        assert!(
+            metadata
+                .parse_warnings
+                .unwrap()
+                .iter()
+                .any(|warning| warning.contains("DefineBitsLossless"))
+        );
+    }
+
+    #[test]
+    fn test_schema_version_opt_in() {
+        let options = ExtractOptions {
+            with_schema_version: true,
+            ..Default::default()
+        };
+        let metadata = metadata_from_tags_with(&[swf::Tag::ShowFrame], &options);
+        assert_eq!(metadata.schema_version, Some(SCHEMA_VERSION));
+        assert!(format(&metadata, Format::Json).starts_with("{\"schema_version\":1,"));
+
+        let metadata = metadata_from_tags(&[swf::Tag::ShowFrame]);
+        assert!(!format(&metadata, Format::Json).contains("schema_version"));
+    }
+
+    #[test]
+    fn test_parse_duration_reported() {
+        let options = ExtractOptions {
+            timings: true,
+            ..Default::default()
+        };
+        let metadata = metadata_from_tags_with(
+            &[define_shape(1, 10.0, 10.0), swf::Tag::ShowFrame],
+            &options,
+        );
+        assert!(metadata.parse_duration_ms.unwrap() > 0.0);
+
+        let metadata = metadata_from_tags(&[swf::Tag::ShowFrame]);
+        assert_eq!(metadata.parse_duration_ms, None);
+
+        // Time spent waiting on the reader isn't parse time
+        struct SlowReader<'a>(&'a [u8]);
+        impl Read for SlowReader<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                self.0.read(buf)
+            }
+        }
+        let data = create_test_swf(&[swf::Tag::ShowFrame]);
+        let metadata = read_metadata("test.swf", SlowReader(&data), &options).unwrap();
+        assert!(metadata.parse_duration_ms.unwrap() < 100.0);
+    }
+
+    #[test]
+    fn test_extract_errors_distinguish_causes() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.swf");
+        let result = extract_with_options(&missing, &ExtractOptions::default());
+        assert!(matches!(result, Err(ExtractError::Io(_))));
+
+        let result = read_metadata(
+            "test.swf",
+            &b"not a swf file"[..],
+            &ExtractOptions::default(),
+        );
+        assert!(matches!(result, Err(ExtractError::Decompress(_))));
+    }
+
+    #[test]
+    fn test_stage_size_precise_keeps_half_pixels() {
+        let mut header = create_test_header();
+        // 11010 x 8010 twips is 550.5 x 400.5 pixels
+        header.stage_size.x_max = swf::Twips::new(11010);
+        header.stage_size.y_max = swf::Twips::new(8010);
+        let data = create_test_swf_with_header(&header, &[swf::Tag::ShowFrame]);
+
+        let metadata = read_metadata("test.swf", &data[..], &ExtractOptions::default()).unwrap();
+        assert_eq!(metadata.stage_size, (550, 400));
+        assert_eq!(metadata.stage_size_precise, (550.5, 400.5));
+    }
+
+    #[test]
+    fn test_stage_size_twips_is_exact() {
+        let mut header = create_test_header();
+        // 11003 twips is 550.15 pixels, which the pixel size rounds away
+        header.stage_size.x_max = swf::Twips::new(11003);
+        header.stage_size.y_max = swf::Twips::new(8000);
+        let data = create_test_swf_with_header(&header, &[swf::Tag::ShowFrame]);
+
+        let metadata = read_metadata("test.swf", &data[..], &ExtractOptions::default()).unwrap();
+        assert_eq!(metadata.stage_size_twips, (11003, 8000));
+        assert_eq!(metadata.stage_size, (550, 400));
+    }
+}
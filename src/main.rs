@@ -1,8 +1,19 @@
-use clap::Parser;
-use std::path::PathBuf;
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::{CommandFactory, FromArgMatches, Parser};
+use extract_metadata::{
+    ExtractError, ExtractOptions, Format, FormatOptions, Metadata, WarningCollector,
+    combine_csv_rows, combine_outputs, csv_error_row, csv_row, format_error_placeholder,
+    format_with_options, read_metadata, reported_file_name, validate_swf,
+};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
 use std::fs::{File, metadata};
-use std::io::{BufReader, Write};
+use std::io::{Read, Write};
 use walkdir::WalkDir;
 
 /// Extract metadata from SWF files
@@ -10,139 +21,1844 @@ use walkdir::WalkDir;
 #[command(name = "extract-metadata")]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Path to the SWF file or directory to extract metadata from
+    /// Path to the SWF file or directory to extract metadata from, or `-` to read a single
+    /// SWF from standard input and print its metadata
     #[arg(short, long, value_name = "PATH")]
     input: PathBuf,
 
-    /// Output format (json, yaml, or text)
-    #[arg(short, long, default_value = "json")]
-    format: String,
+    /// Output format
+    #[arg(
+        short,
+        long,
+        default_value = "json",
+        value_parser = PossibleValuesParser::new(Format::NAMES)
+            .map(|name| Format::from_name(&name).unwrap())
+    )]
+    format: Format,
+
+    /// With --format text, print each file's metadata on a single line (rejected otherwise)
+    #[arg(long)]
+    compact_text: bool,
+
+    /// With --format json or jsonl, how to write 64-bit unsigned fields such as byte counts
+    #[arg(long, value_enum, value_name = "MODE", default_value_t = JsonNumberMode::Number)]
+    json_number_mode: JsonNumberMode,
+
+    /// With --format csv, the character separating fields, e.g. `;` for spreadsheets
+    /// that read `,` as a decimal separator (rejected otherwise)
+    #[arg(long, value_name = "CHAR")]
+    delimiter: Option<char>,
 
     /// Verbose output
     #[arg(short, long)]
     verbose: bool,
+
+    /// Print nothing but errors on standard error
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Include recoverable parse warnings in the output (implied by --verbose)
+    #[arg(long)]
+    warnings: bool,
+
+    /// Validate ImportAssets against exporting SWFs found in the same directory
+    #[arg(long)]
+    dereference_imports: bool,
+
+    /// Include display list statistics gathered from PlaceObject tags
+    #[arg(long)]
+    displaylist: bool,
+
+    /// Include information about embedded fonts
+    #[arg(long)]
+    fonts: bool,
+
+    /// Include an estimate of the memory needed to hold all decoded bitmaps
+    #[arg(long)]
+    bitmaps: bool,
+
+    /// Include statistics about unused or empty content
+    #[arg(long)]
+    dead_code: bool,
+
+    /// Include information about ActionScript code
+    #[arg(long)]
+    scripts: bool,
+
+    /// Include information about vector shapes
+    #[arg(long)]
+    shapes: bool,
+
+    /// Include information about sounds
+    #[arg(long)]
+    audio: bool,
+
+    /// Include information about embedded video
+    #[arg(long)]
+    video: bool,
+
+    /// Include information about embedded binary data
+    #[arg(long)]
+    binary_data: bool,
+
+    /// Include information about buttons and their event handlers
+    #[arg(long)]
+    buttons: bool,
+
+    /// Include information about ActionScript 3 classes and libraries
+    #[arg(long)]
+    classes: bool,
+
+    /// Include statistics about the raw tag stream
+    #[arg(long)]
+    tags: bool,
+
+    /// Include how many times each tag type occurs
+    #[arg(long)]
+    tag_counts: bool,
+
+    /// With --tag-counts, describe what each counted tag type holds
+    #[arg(long, requires = "tag_counts")]
+    describe_tags: bool,
+
+    /// Include how long decompressing and parsing each file took
+    #[arg(long)]
+    timings: bool,
+
+    /// Include the output schema version as the first field of every sidecar
+    #[arg(long)]
+    with_schema_version: bool,
+
+    /// Flag frame rates above this many frames per second as suspicious [default: 120]
+    #[arg(long, value_name = "FPS")]
+    max_sane_fps: Option<f32>,
+
+    /// Include the frame rate snapped to the nearest of 12, 24, 25, 30 and 60 fps when
+    /// within 0.1 fps of it
+    #[arg(long)]
+    normalize_frame_rate: bool,
+
+    /// Write each sidecar to a path built from this pattern instead of next to the SWF
+    #[arg(long, value_name = "PATTERN", value_parser = parse_output_template)]
+    output_template: Option<String>,
+
+    /// Write sidecars under this directory instead of next to each SWF, mirroring the
+    /// input directory's layout
+    #[arg(long, value_name = "DIR", conflicts_with = "output_template")]
+    output_dir: Option<PathBuf>,
+
+    /// Leave files alone whose sidecar already exists and is newer than the SWF
+    #[arg(long, conflicts_with = "stdout")]
+    skip_existing: bool,
+
+    /// Write a `.sha256` file holding each sidecar's digest next to it
+    #[arg(long)]
+    checksum_sidecar: bool,
+
+    /// Fail a file instead of writing its metadata when the serialized output is larger
+    /// than this many bytes; --on-error decides what happens next
+    #[arg(long, value_name = "BYTES", conflicts_with_all = ["group_by", "delta_against", "summary_only"])]
+    max_output_bytes: Option<usize>,
+
+    /// Process directory entries in lexicographic path order instead of filesystem order
+    #[arg(long)]
+    deterministic_order: bool,
+
+    /// Strip this prefix from each reported file_name
+    #[arg(long, value_name = "PATH")]
+    trim_prefix: Option<PathBuf>,
+
+    /// Leave file names that don't start with --trim-prefix untouched instead of failing
+    #[arg(long)]
+    lenient: bool,
+
+    /// What to do when a file in a directory fails to process
+    #[arg(long, value_enum, default_value_t = OnError::Skip)]
+    on_error: OnError,
+
+    /// Only write metadata for directory entries matching this expression
+    #[arg(long, value_name = "EXPR", value_parser = Filter::parse)]
+    filter: Option<Filter>,
+
+    /// In directory mode, only process files whose path relative to the input directory
+    /// matches this pattern, instead of every `.swf` file
+    #[arg(long, value_name = "PATTERN", value_parser = Glob::parse)]
+    glob: Option<Glob>,
+
+    /// In directory mode, skip files whose SHA-256 matches their entry in this `sha256sum`
+    /// manifest of paths relative to the input directory
+    #[arg(long, value_name = "MANIFEST")]
+    since_hash: Option<PathBuf>,
+
+    /// Print a Merkle root over the content hashes of every SWF in the directory
+    #[arg(long)]
+    hash_tree: bool,
+
+    /// Abort a directory run once it has taken longer than this many milliseconds
+    #[arg(long, value_name = "MS")]
+    timeout_total_ms: Option<u64>,
+
+    /// Stop with a non-zero exit code at the first file that looks like a preloader stub
+    /// (see `is_preloader`)
+    #[arg(long, requires = "scripts", conflicts_with_all = ["group_by", "delta_against"])]
+    stop_on_stub: bool,
+
+    /// Write no metadata for files that look like preloader stubs (see `is_preloader`)
+    #[arg(
+        long,
+        requires = "scripts",
+        conflicts_with_all = ["stop_on_stub", "group_by", "delta_against"]
+    )]
+    exclude_empty: bool,
+
+    /// Parse directory entries on a pool of workers feeding a dedicated writer thread
+    #[arg(long)]
+    parallel_io: bool,
+
+    /// Print metadata to standard output instead of writing sidecar files; a directory
+    /// prints a single JSON array, YAML sequence or plist array of every result
+    #[arg(long)]
+    stdout: bool,
+
+    /// With --stdout in directory mode, print the files grouped by this field instead of
+    /// their full metadata
+    #[arg(long, value_enum, value_name = "FIELD", requires = "stdout")]
+    group_by: Option<GroupBy>,
+
+    /// In directory mode, print which files were added, removed or changed relative to
+    /// this previous `--stdout --format json` aggregate instead of writing sidecars
+    #[arg(long, value_name = "FILE", conflicts_with = "group_by")]
+    delta_against: Option<PathBuf>,
+
+    /// After a directory run, report totals over every file processed on stderr, or in
+    /// `summary.json` under --output-dir
+    #[arg(long)]
+    summary: bool,
+
+    /// Like --summary, but write no sidecars or combined output, only the summary
+    #[arg(long, conflicts_with_all = ["stdout", "group_by", "delta_against"])]
+    summary_only: bool,
+
+    /// When a directory holds no SWF files, still write an empty aggregate,
+    /// `metadata.<format>`, into the directory or --output-dir
+    #[arg(long, conflicts_with_all = ["stdout", "group_by", "delta_against", "summary_only"])]
+    emit_empty_aggregate: bool,
+
+    /// Only print how many SWF files the input holds, without parsing any of them
+    #[arg(long)]
+    count_only: bool,
+
+    /// Only check that each SWF parses, printing a final count and exiting non-zero if any fails
+    #[arg(long)]
+    validate_only: bool,
+}
+
+/// Directory-mode policy for files that fail to process
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+enum OnError {
+    /// Report the error and continue with the next file
+    #[default]
+    Skip,
+    /// Stop the run with a non-zero exit code
+    Stop,
+    /// Write a sidecar holding only the file name and the error, then continue
+    Placeholder,
+}
+
+/// How `--json-number-mode` writes `u64` fields
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum JsonNumberMode {
+    /// As JSON numbers
+    Number,
+    /// As JSON strings, so readers that parse numbers as doubles keep every digit
+    String,
+}
+
+/// Field a `--group-by` report groups files on
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum GroupBy {
+    #[value(name = "stage_size")]
+    StageSize,
+    #[value(name = "frame_rate")]
+    FrameRate,
+    Version,
+}
+
+impl GroupBy {
+    fn key(self, metadata: &Metadata) -> String {
+        match self {
+            GroupBy::StageSize => format!("{}x{}", metadata.stage_size.0, metadata.stage_size.1),
+            GroupBy::FrameRate => metadata.frame_rate.to_string(),
+            GroupBy::Version => metadata.swf_version.to_string(),
+        }
+    }
+}
+
+/// Options controlling how a directory run discovers and handles files
+#[derive(Debug, Default)]
+struct DirectoryOptions {
+    deterministic_order: bool,
+    lenient: bool,
+    on_error: OnError,
+    filter: Option<Filter>,
+    glob: Option<Glob>,
+    /// Content hashes by relative path, loaded from the `--since-hash` manifest
+    since_hashes: Option<BTreeMap<PathBuf, String>>,
+    hash_tree: bool,
+    timeout_total: Option<Duration>,
+    stop_on_stub: bool,
+    exclude_empty: bool,
+    output_template: Option<String>,
+    output_dir: Option<PathBuf>,
+    skip_existing: bool,
+    checksum_sidecar: bool,
+    max_output_bytes: Option<usize>,
+    parallel_io: bool,
+    stdout: bool,
+    group_by: Option<GroupBy>,
+    /// Record hashes by file name, loaded from the `--delta-against` aggregate
+    baseline: Option<BTreeMap<String, String>>,
+    summary: bool,
+    /// Whether --summary-only suppresses everything but the summary
+    summary_only: bool,
+    emit_empty_aggregate: bool,
+    format_options: FormatOptions,
+}
+
+/// How far a directory run got
+#[derive(Debug, PartialEq)]
+struct DirectorySummary {
+    completed: usize,
+    failed: usize,
+    timed_out: bool,
+    /// Whether --stop-on-stub ended the run at a preloader stub
+    stub_found: bool,
+    /// Whether --hash-tree could not read every file
+    hash_failed: bool,
+}
+
+/// The files sharing one `--group-by` key
+#[derive(serde::Serialize, Debug, Default, PartialEq)]
+struct Group {
+    count: usize,
+    files: Vec<String>,
+}
+
+/// Totals over a directory run, reported by `--summary`
+#[derive(serde::Serialize, Debug, Default, PartialEq)]
+struct RunSummary {
+    files_processed: usize,
+    files_failed: usize,
+    total_frames: u64,
+    min_frame_rate: Option<f32>,
+    max_frame_rate: Option<f32>,
+    average_frame_rate: Option<f32>,
+    /// How many files have each `<width>x<height>` stage size
+    stage_sizes: BTreeMap<String, usize>,
 }
 
-#[derive(serde::Serialize, Debug, PartialEq)]
-struct Metadata {
-    file_name: String,
-    stage_size: (u32, u32),
-    no_of_frames: u32,
-    frame_rate: u32,
+impl RunSummary {
+    fn new(metadata: &[Metadata], failed: usize) -> RunSummary {
+        let frame_rates = metadata.iter().map(|metadata| metadata.frame_rate);
+        let mut stage_sizes = BTreeMap::new();
+        for metadata in metadata {
+            let (width, height) = metadata.stage_size;
+            *stage_sizes
+                .entry(format!("{}x{}", width, height))
+                .or_insert(0) += 1;
+        }
+        RunSummary {
+            files_processed: metadata.len(),
+            files_failed: failed,
+            total_frames: metadata
+                .iter()
+                .map(|metadata| metadata.no_of_frames as u64)
+                .sum(),
+            min_frame_rate: frame_rates.clone().reduce(f32::min),
+            max_frame_rate: frame_rates.clone().reduce(f32::max),
+            average_frame_rate: (!metadata.is_empty())
+                .then(|| frame_rates.sum::<f32>() / metadata.len() as f32),
+            stage_sizes,
+        }
+    }
+
+    fn to_text(&self) -> String {
+        let rate = |rate: Option<f32>| rate.map_or("none".to_string(), |rate| rate.to_string());
+        let stage_sizes: Vec<String> = self
+            .stage_sizes
+            .iter()
+            .map(|(size, count)| format!("{}: {}", size, count))
+            .collect();
+        format!(
+            "Files Processed: {}\nFiles Failed: {}\nTotal Frames: {}\nFrame Rate: min {}, max {}, average {}\nStage Sizes: {}",
+            self.files_processed,
+            self.files_failed,
+            self.total_frames,
+            rate(self.min_frame_rate),
+            rate(self.max_frame_rate),
+            rate(self.average_frame_rate),
+            stage_sizes.join(", ")
+        )
+    }
 }
 
+/// The files a `--delta-against` run found added, removed or changed
+#[derive(serde::Serialize, Debug, Default, PartialEq)]
+struct Delta {
+    added: Vec<String>,
+    removed: Vec<String>,
+    changed: Vec<String>,
+}
+
+/// Set by `--quiet` to silence the per-file progress line
+static QUIET: AtomicBool = AtomicBool::new(false);
+
 fn main() {
-    let args = Args::parse();
+    let matches = Args::command().get_matches();
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+    QUIET.store(args.quiet, Ordering::Relaxed);
+    // The library leaves the process-wide logger to its host; ours only feeds
+    // parse_warnings
+    if log::set_logger(&WarningCollector).is_ok() {
+        log::set_max_level(log::LevelFilter::Warn);
+    }
+
+    let options = ExtractOptions {
+        warnings: args.warnings || args.verbose,
+        dereference_imports: args.dereference_imports,
+        displaylist: args.displaylist,
+        fonts: args.fonts,
+        bitmaps: args.bitmaps,
+        dead_code: args.dead_code,
+        scripts: args.scripts,
+        shapes: args.shapes,
+        audio: args.audio,
+        video: args.video,
+        binary_data: args.binary_data,
+        buttons: args.buttons,
+        classes: args.classes,
+        tags: args.tags,
+        tag_counts: args.tag_counts,
+        describe_tags: args.describe_tags,
+        timings: args.timings,
+        with_schema_version: args.with_schema_version,
+        max_sane_fps: args.max_sane_fps,
+        normalize_frame_rate: args.normalize_frame_rate,
+        trim_prefix: args.trim_prefix.clone(),
+    };
+    let format = args.format;
+    let format_options = format_options(&args).unwrap_or_else(|error| error.exit());
+    check_report_options(&args).unwrap_or_else(|error| error.exit());
 
     if args.verbose {
         eprintln!("Input path: {:?}", args.input);
-        eprintln!("Format: {}", args.format);
+        eprintln!("Format: {}", args.format.name());
+    }
+
+    // There is no input path to name a sidecar after, so metadata read from standard
+    // input is always printed
+    if args.input == Path::new(STDIN_INPUT) {
+        check_directory_only_args(&matches).unwrap_or_else(|error| error.exit());
+        check_stdin_args(&args).unwrap_or_else(|error| error.exit());
+        check_trim_prefix(&[PathBuf::from(STDIN_FILE_NAME)], &options, args.lenient);
+        match load_stdin_metadata(std::io::stdin().lock(), &options) {
+            Ok(metadata) if !keep_single_input(&metadata, &args, STDIN_FILE_NAME) => {}
+            Ok(metadata) => {
+                let output = format_with_options(&metadata, format, &format_options);
+                if let Err(error) = check_output_size(&output, args.max_output_bytes) {
+                    eprintln!("Error: {}: {}", STDIN_FILE_NAME, error);
+                    std::process::exit(1);
+                }
+                println!("{}", output)
+            }
+            Err(error) => {
+                eprintln!("Error: {}: {}", STDIN_FILE_NAME, error);
+                std::process::exit(1);
+            }
+        }
+        return;
     }
 
     // Check if input is a file or directory
-    let meta = metadata(&args.input).expect("Failed to read input path");
+    let meta = match metadata(&args.input) {
+        Ok(meta) => meta,
+        Err(error) => {
+            eprintln!(
+                "Error: Failed to read input path {:?}: {}",
+                args.input, error
+            );
+            std::process::exit(1);
+        }
+    };
+
+    if !meta.is_dir() {
+        check_directory_only_args(&matches).unwrap_or_else(|error| error.exit());
+    }
+
+    if args.count_only {
+        println!("{}", count_swf_files(&args.input, args.glob.as_ref()));
+        return;
+    }
+
+    if args.validate_only {
+        let files = discover_files(
+            &args.input,
+            args.glob.as_ref(),
+            args.deterministic_order,
+            args.verbose,
+        );
+        let invalid = validate_files(&files, args.verbose);
+        println!(
+            "{} of {} SWF file(s) valid",
+            files.len() - invalid,
+            files.len()
+        );
+        if invalid > 0 {
+            std::process::exit(1);
+        }
+        return;
+    }
 
     if meta.is_file() {
         // Process single file
         if args.verbose {
             eprintln!("Processing single file...");
         }
-        let output = extract_metadata(args.input.to_str().unwrap(), &args.format);
-        let output_path = args.input.with_extension(format!("swf.{}", args.format));
-        save_metadata(&output_path, &output, args.verbose);
+        check_trim_prefix(std::slice::from_ref(&args.input), &options, args.lenient);
+        let sidecar = (!args.stdout).then(|| {
+            let input = match (&args.output_dir, args.input.file_name()) {
+                (Some(output_dir), Some(name)) => output_dir.join(name),
+                _ => args.input.clone(),
+            };
+            output_path(&input, format, args.output_template.as_deref())
+        });
+        if args.skip_existing
+            && let Some(sidecar) = &sidecar
+            && sidecar_is_current(sidecar, &args.input)
+        {
+            if args.verbose {
+                eprintln!("Skipping {:?}: {:?} is up to date", args.input, sidecar);
+            }
+            return;
+        }
+        let output = match load_metadata(&args.input, &options) {
+            Ok(metadata) if !keep_single_input(&metadata, &args, &format!("{:?}", args.input)) => {
+                return;
+            }
+            Ok(metadata) => format_with_options(&metadata, format, &format_options),
+            Err(error) => {
+                eprintln!("Error: {:?}: {}", args.input, error);
+                std::process::exit(1);
+            }
+        };
+        if let Err(error) = check_output_size(&output, args.max_output_bytes) {
+            eprintln!("Error: {:?}: {}", args.input, error);
+            std::process::exit(1);
+        }
+        match sidecar {
+            Some(output_path) => {
+                save_metadata(&output_path, &output, args.checksum_sidecar, args.verbose)
+            }
+            None => println!("{}", output),
+        }
     } else if meta.is_dir() {
         // Process directory
         if args.verbose {
             eprintln!("Processing directory recursively...");
         }
-        process_directory(&args.input, &args.format, args.verbose);
+        let baseline = args.delta_against.as_deref().map(|path| {
+            load_baseline(path).unwrap_or_else(|error| {
+                eprintln!(
+                    "Error: Failed to read --delta-against {:?}: {}",
+                    path, error
+                );
+                std::process::exit(1);
+            })
+        });
+        let since_hashes = args.since_hash.as_deref().map(|path| {
+            load_hash_manifest(path).unwrap_or_else(|error| {
+                eprintln!("Error: Failed to read --since-hash {:?}: {}", path, error);
+                std::process::exit(1);
+            })
+        });
+        let dir_options = DirectoryOptions {
+            deterministic_order: args.deterministic_order,
+            lenient: args.lenient,
+            on_error: args.on_error,
+            filter: args.filter.clone(),
+            glob: args.glob.clone(),
+            since_hashes,
+            hash_tree: args.hash_tree,
+            timeout_total: args.timeout_total_ms.map(Duration::from_millis),
+            stop_on_stub: args.stop_on_stub,
+            exclude_empty: args.exclude_empty,
+            output_template: args.output_template.clone(),
+            output_dir: args.output_dir.clone(),
+            skip_existing: args.skip_existing,
+            checksum_sidecar: args.checksum_sidecar,
+            max_output_bytes: args.max_output_bytes,
+            parallel_io: args.parallel_io,
+            stdout: args.stdout,
+            group_by: args.group_by,
+            baseline,
+            summary: args.summary || args.summary_only,
+            summary_only: args.summary_only,
+            emit_empty_aggregate: args.emit_empty_aggregate,
+            format_options,
+        };
+        let summary = process_directory(&args.input, format, &options, &dir_options, args.verbose);
+        if summary.timed_out || summary.stub_found || summary.hash_failed || summary.failed > 0 {
+            std::process::exit(1);
+        }
     } else {
         eprintln!("Error: Input path is neither a file nor a directory");
         std::process::exit(1);
     }
 }
 
-fn extract_metadata(file_name: &str, format: &str) -> String {
-    println!("Extracting metadata from: {:?}", file_name);
-    let file = File::open(file_name).unwrap();
-    let reader = BufReader::new(file);
-    let swf_buf = swf::decompress_swf(reader).unwrap();
-    let swf = swf::parse_swf(&swf_buf).unwrap();
+/// Extracts one file for the command line, reporting progress on stderr, unless
+/// `--quiet`, so `--stdout` output stays clean.
+fn load_metadata(path: &Path, options: &ExtractOptions) -> Result<Metadata, ExtractError> {
+    if !QUIET.load(Ordering::Relaxed) {
+        eprintln!("Extracting metadata from: {:?}", path);
+    }
+    extract_metadata::extract_with_options(path, options)
+}
+
+/// The `--input` value that reads a SWF from standard input
+const STDIN_INPUT: &str = "-";
+
+/// The `file_name` reported for a SWF read from standard input
+const STDIN_FILE_NAME: &str = "<stdin>";
+
+fn load_stdin_metadata(
+    reader: impl Read,
+    options: &ExtractOptions,
+) -> Result<Metadata, ExtractError> {
+    if !QUIET.load(Ordering::Relaxed) {
+        eprintln!("Extracting metadata from: {}", STDIN_FILE_NAME);
+    }
+    read_metadata(STDIN_FILE_NAME, reader, options)
+}
+
+/// Rejects flags that can't work on standard input: imports are resolved next to
+/// the importing file, which standard input doesn't have.
+fn check_stdin_args(args: &Args) -> Result<(), clap::Error> {
+    if args.dereference_imports {
+        return Err(Args::command().error(
+            clap::error::ErrorKind::ArgumentConflict,
+            "--dereference-imports cannot be used when reading standard input",
+        ));
+    }
+    Ok(())
+}
+
+/// Applies `--stop-on-stub`, `--exclude-empty` and `--filter` to the metadata of a
+/// single input called `name`, returning whether it should be written.
+///
+/// A stub under `--stop-on-stub` ends the process with a non-zero code.
+fn keep_single_input(metadata: &Metadata, args: &Args, name: &str) -> bool {
+    let is_stub = metadata.is_preloader == Some(true);
+    if args.stop_on_stub && is_stub {
+        eprintln!("Error: {} is a preloader stub", name);
+        std::process::exit(1);
+    }
+    if args.exclude_empty && is_stub {
+        if args.verbose {
+            eprintln!("Skipping {}: preloader stub", name);
+        }
+        return false;
+    }
+    matches_filter(metadata, args.filter.as_ref(), args.verbose)
+}
+
+/// Checks a file's serialized metadata against `--max-output-bytes`.
+fn check_output_size(output: &str, max_output_bytes: Option<usize>) -> Result<(), String> {
+    match max_output_bytes {
+        Some(max) if output.len() > max => Err(format!(
+            "metadata is {} bytes, over the --max-output-bytes limit of {}",
+            output.len(),
+            max
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Name of the single CSV file a directory run writes into the directory
+const COMBINED_CSV_NAME: &str = "metadata.csv";
 
-    let stage_size = swf.header.stage_size();
+/// Name of the file `--summary` writes under `--output-dir`
+const SUMMARY_NAME: &str = "summary.json";
 
-    let metadata = Metadata {
-        file_name: file_name.to_string(),
-        stage_size: (
-            (stage_size.x_max - stage_size.x_min).to_pixels() as u32,
-            (stage_size.y_max - stage_size.y_min).to_pixels() as u32,
-        ),
-        no_of_frames: swf.header.num_frames() as u32,
-        frame_rate: swf.header.frame_rate().to_f32() as u32,
+fn process_directory(
+    dir_path: &PathBuf,
+    format: Format,
+    options: &ExtractOptions,
+    dir_options: &DirectoryOptions,
+    verbose: bool,
+) -> DirectorySummary {
+    let start = Instant::now();
+    // Everything the directory holds; --since-hash and --skip-existing only narrow down
+    // what gets extracted, so --hash-tree still covers all of it
+    let discovered = discover_files(
+        dir_path,
+        dir_options.glob.as_ref(),
+        dir_options.deterministic_order,
+        verbose,
+    );
+    let swf_files = files_to_extract(&discovered, dir_path, dir_options, verbose);
+    check_trim_prefix(&swf_files, options, dir_options.lenient);
+    // Set where --timeout-total-ms fires, so other early stops aren't taken for a timeout
+    let deadline_passed = AtomicBool::new(false);
+    let timed_out = || {
+        let passed = dir_options
+            .timeout_total
+            .is_some_and(|timeout| start.elapsed() >= timeout);
+        if passed {
+            deadline_passed.store(true, Ordering::Relaxed);
+        }
+        passed
     };
+    // Set by --stop-on-stub at the first preloader stub, ending the run
+    let stub_found = AtomicBool::new(false);
+    // Set by --on-error stop at the first file that fails
+    let halted = AtomicBool::new(false);
+    let stop =
+        || timed_out() || stub_found.load(Ordering::Relaxed) || halted.load(Ordering::Relaxed);
+
+    // --group-by, --delta-against and --summary-only report on the metadata instead of
+    // writing it out
+    let report = dir_options.group_by.is_some()
+        || dir_options.baseline.is_some()
+        || dir_options.summary_only;
+    // JSON Lines on stdout is written record by record as files finish
+    let stream = dir_options.stdout && format == Format::Jsonl;
+    // Otherwise with --stdout, and for CSV, results are gathered in file order and
+    // emitted as a single document at the end
+    let aggregate =
+        !stream && !dir_options.summary_only && (dir_options.stdout || format == Format::Csv);
+    // A combined CSV is always rewritten in full, so only sidecars can be skipped
+    let swf_files = if dir_options.skip_existing && !aggregate && !report {
+        without_current_sidecars(swf_files, dir_path, format, dir_options, verbose)
+    } else {
+        swf_files
+    };
+    let mut printed = Vec::new();
+    let write = |index: usize, output_path: PathBuf, content: String| {
+        if stream {
+            let mut stdout = std::io::stdout().lock();
+            let _ = writeln!(stdout, "{}", content).and_then(|_| stdout.flush());
+        } else if aggregate {
+            printed.push((index, content));
+        } else {
+            save_metadata(
+                &output_path,
+                &content,
+                dir_options.checksum_sidecar,
+                verbose,
+            );
+        }
+    };
+
+    // Files --delta-against leaves out of both sides because they failed or didn't match
+    // --filter
+    let excluded = Mutex::new(Vec::new());
+    let exclude = |path: &Path| {
+        if dir_options.baseline.is_some() {
+            excluded
+                .lock()
+                .unwrap()
+                .push(reported_file_name(path, options));
+        }
+    };
+    // Metadata is only kept past formatting when --summary or a report needs it
+    let processed = Mutex::new(Vec::new());
+    // Applies --on-error to a failed entry
+    let fail = |path: &Path, error: &dyn std::fmt::Display| {
+        exclude(path);
+        if dir_options.on_error == OnError::Stop {
+            halted.store(true, Ordering::Relaxed);
+        }
+        (
+            handle_failure(path, dir_path, error, format, dir_options),
+            true,
+        )
+    };
+    // Extracts one entry into the sidecar to write, if any, and whether it failed
+    let extract =
+        |path: &Path| match process_file(path, dir_path, format, options, dir_options, verbose) {
+            Ok(Some((_, metadata)))
+                if dir_options.stop_on_stub && metadata.is_preloader == Some(true) =>
+            {
+                eprintln!("Error: {:?} is a preloader stub", path);
+                stub_found.store(true, Ordering::Relaxed);
+                (None, false)
+            }
+            Ok(Some((_, metadata)))
+                if dir_options.exclude_empty && metadata.is_preloader == Some(true) =>
+            {
+                if verbose {
+                    eprintln!("Skipping {:?}: preloader stub", path);
+                }
+                (None, false)
+            }
+            Ok(Some((_, metadata))) if report => {
+                processed.lock().unwrap().push(metadata);
+                (None, false)
+            }
+            Ok(Some((output_path, metadata))) => {
+                // A combined CSV is built from bare rows, under a header added at the end
+                let content = if format == Format::Csv {
+                    csv_row(&metadata, &dir_options.format_options)
+                } else {
+                    format_with_options(&metadata, format, &dir_options.format_options)
+                };
+                if let Err(error) = check_output_size(&content, dir_options.max_output_bytes) {
+                    return fail(path, &error);
+                }
+                if dir_options.summary {
+                    processed.lock().unwrap().push(metadata);
+                }
+                (Some((output_path, content)), false)
+            }
+            Ok(None) => {
+                exclude(path);
+                (None, false)
+            }
+            Err(error) => fail(path, &error),
+        };
+
+    let (completed, failed) = if dir_options.parallel_io {
+        process_files_pipelined(&swf_files, extract, stop, write)
+    } else {
+        let mut write = write;
+        let (mut completed, mut failed) = (0, 0);
+        for (index, path) in swf_files.iter().enumerate() {
+            if stop() {
+                break;
+            }
+            let (sidecar, file_failed) = extract(path);
+            failed += file_failed as usize;
+            if let Some((output_path, content)) = sidecar {
+                write(index, output_path, content);
+            }
+            completed += 1;
+        }
+        (completed, failed)
+    };
+
+    // --on-error stop abandons the run, combined output included
+    if halted.load(Ordering::Relaxed) {
+        return DirectorySummary {
+            completed,
+            failed,
+            timed_out: false,
+            stub_found: false,
+            hash_failed: false,
+        };
+    }
+
+    let processed = processed.into_inner().unwrap();
+    if let Some(group_by) = dir_options.group_by {
+        println!(
+            "{}",
+            format_groups(&group_files(&processed, group_by), format)
+        );
+    } else if let Some(baseline) = &dir_options.baseline {
+        // Files a timeout left unread would show up as removed
+        if !deadline_passed.load(Ordering::Relaxed) {
+            let excluded = excluded.into_inner().unwrap();
+            let delta = delta_files(&processed, &excluded, baseline);
+            println!("{}", format_delta(&delta, format));
+        }
+    } else if aggregate {
+        printed.sort_by_key(|(index, _)| *index);
+        let outputs: Vec<String> = printed.into_iter().map(|(_, content)| content).collect();
+        let combined = if format == Format::Csv {
+            combine_csv_rows(&outputs, &dir_options.format_options)
+        } else {
+            combine_outputs(&outputs, format, &dir_options.format_options)
+        };
+        if dir_options.stdout {
+            println!("{}", combined);
+        } else if !discovered.is_empty() || dir_options.emit_empty_aggregate {
+            let csv_dir = dir_options.output_dir.as_deref().unwrap_or(dir_path);
+            save_metadata(
+                &csv_dir.join(COMBINED_CSV_NAME),
+                &combined,
+                dir_options.checksum_sidecar,
+                verbose,
+            );
+        }
+    } else if discovered.is_empty() && dir_options.emit_empty_aggregate {
+        // Sidecar formats have no combined file, so the empty one is named like the CSV
+        let aggregate_dir = dir_options.output_dir.as_deref().unwrap_or(dir_path);
+        save_metadata(
+            &aggregate_dir.join(format!("metadata.{}", format.name())),
+            &combine_outputs(&[], format, &dir_options.format_options),
+            dir_options.checksum_sidecar,
+            verbose,
+        );
+    }
+
+    if dir_options.summary {
+        let summary = RunSummary::new(&processed, failed);
+        match &dir_options.output_dir {
+            Some(output_dir) => save_metadata(
+                &output_dir.join(SUMMARY_NAME),
+                &serde_json::to_string(&summary).unwrap(),
+                false,
+                verbose,
+            ),
+            None => eprintln!("{}", summary.to_text()),
+        }
+    }
+
+    if stub_found.load(Ordering::Relaxed) {
+        return DirectorySummary {
+            completed,
+            failed,
+            timed_out: false,
+            stub_found: true,
+            hash_failed: false,
+        };
+    }
+    if deadline_passed.load(Ordering::Relaxed) {
+        eprintln!(
+            "Error: --timeout-total-ms exceeded after {} of {} SWF file(s)",
+            completed,
+            swf_files.len()
+        );
+        return DirectorySummary {
+            completed,
+            failed,
+            timed_out: true,
+            stub_found: false,
+            hash_failed: false,
+        };
+    }
+
+    let mut hash_failed = false;
+    if dir_options.hash_tree {
+        match archive_root_hash(dir_path, &discovered) {
+            Ok(hash) => println!("archive_root_hash: {}", hash),
+            Err(error) => {
+                eprintln!("Error: Failed to compute archive_root_hash: {}", error);
+                hash_failed = true;
+            }
+        }
+    }
+
+    if failed > 0 {
+        eprintln!(
+            "Error: Failed to extract metadata from {} of {} SWF file(s)",
+            failed,
+            swf_files.len()
+        );
+    }
+    if verbose {
+        eprintln!("Processed {} SWF file(s)", swf_files.len());
+    }
+    DirectorySummary {
+        completed,
+        failed,
+        timed_out: false,
+        stub_found: false,
+        hash_failed,
+    }
+}
+
+/// Narrows `discovered` down to the files a run extracts: those `--since-hash` finds
+/// changed, except for `--delta-against`, which compares the whole directory so that a
+/// file left out isn't reported as removed.
+fn files_to_extract(
+    discovered: &[PathBuf],
+    dir_path: &Path,
+    dir_options: &DirectoryOptions,
+    verbose: bool,
+) -> Vec<PathBuf> {
+    match &dir_options.since_hashes {
+        Some(hashes) if dir_options.baseline.is_none() => {
+            changed_files(discovered.to_vec(), dir_path, hashes, verbose)
+        }
+        _ => discovered.to_vec(),
+    }
+}
+
+/// Groups `metadata` by its `group_by` key, listing each group's files by name.
+fn group_files(metadata: &[Metadata], group_by: GroupBy) -> BTreeMap<String, Group> {
+    let mut groups: BTreeMap<String, Group> = BTreeMap::new();
+    for metadata in metadata {
+        let group = groups.entry(group_by.key(metadata)).or_default();
+        group.count += 1;
+        group.files.push(metadata.file_name.clone());
+    }
+    // Workers finish in any order under --parallel-io
+    for group in groups.values_mut() {
+        group.files.sort();
+    }
+    groups
+}
+
+fn format_groups(groups: &BTreeMap<String, Group>, format: Format) -> String {
+    serialize_report(groups, format).unwrap_or_else(|| {
+        groups
+            .iter()
+            .map(|(key, group)| format_file_list(key, &group.files))
+            .collect::<Vec<_>>()
+            .join("\n")
+    })
+}
+
+/// Collects the flags that refine `--format`, rejecting a `--compact-text` or
+/// `--json-number-mode string` the chosen format has no use for.
+fn format_options(args: &Args) -> Result<FormatOptions, clap::Error> {
+    let string_numbers = args.json_number_mode == JsonNumberMode::String;
+    if args.compact_text && args.format != Format::Text {
+        return Err(Args::command().error(
+            clap::error::ErrorKind::ArgumentConflict,
+            "--compact-text can only be used with --format text",
+        ));
+    }
+    if string_numbers && !matches!(args.format, Format::Json | Format::Jsonl) {
+        return Err(Args::command().error(
+            clap::error::ErrorKind::ArgumentConflict,
+            "--json-number-mode string can only be used with --format json or jsonl",
+        ));
+    }
+    if let Some(delimiter) = args.delimiter {
+        if args.format != Format::Csv {
+            return Err(Args::command().error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "--delimiter can only be used with --format csv",
+            ));
+        }
+        // Numbers are written unquoted, so they must not contain the delimiter either
+        if delimiter.is_ascii_digit() || matches!(delimiter, '"' | '\n' | '\r' | '.' | '-') {
+            return Err(Args::command().error(
+                clap::error::ErrorKind::InvalidValue,
+                "--delimiter cannot be a quote, a line break or part of a number",
+            ));
+        }
+    }
+    Ok(FormatOptions {
+        compact_text: args.compact_text,
+        string_numbers,
+        csv_delimiter: args.delimiter.unwrap_or(','),
+    })
+}
+
+/// Rejects an `--on-error placeholder` with `--group-by`, `--delta-against` or
+/// `--summary-only`, which write no per-file records for a placeholder to stand in for.
+fn check_report_options(args: &Args) -> Result<(), clap::Error> {
+    if args.on_error == OnError::Placeholder
+        && (args.group_by.is_some() || args.delta_against.is_some() || args.summary_only)
+    {
+        return Err(Args::command().error(
+            clap::error::ErrorKind::ArgumentConflict,
+            "--on-error placeholder cannot be used with --group-by, --delta-against or --summary-only",
+        ));
+    }
+    Ok(())
+}
+
+/// Serializes a directory report in `format`, or returns `None` for the text formats.
+fn serialize_report<T: serde::Serialize>(report: &T, format: Format) -> Option<String> {
+    match format {
+        Format::Json | Format::Jsonl => Some(serde_json::to_string(report).unwrap()),
+        Format::Yaml => Some(serde_yaml::to_string(report).unwrap()),
+        Format::Plist => {
+            let mut plist = Vec::new();
+            plist::to_writer_xml(&mut plist, report).unwrap();
+            Some(String::from_utf8(plist).unwrap())
+        }
+        _ => None,
+    }
+}
+
+fn format_file_list(label: &str, files: &[String]) -> String {
+    let mut text = format!("{}: {}", label, files.len());
+    for file in files {
+        text.push_str(&format!("\n  - {}", file));
+    }
+    text
+}
+
+/// Reads a `--stdout --format json` aggregate into a map from each record's file name
+/// to the `sha256` of the file it was extracted from.
+fn load_baseline(path: &Path) -> Result<BTreeMap<String, String>, String> {
+    let contents = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+    let records: Vec<serde_json::Value> =
+        serde_json::from_str(&contents).map_err(|error| error.to_string())?;
+    records
+        .iter()
+        .map(
+            |record| match (record["file_name"].as_str(), record["sha256"].as_str()) {
+                (Some(file_name), Some(hash)) => Ok((file_name.to_string(), hash.to_string())),
+                (None, _) => Err("record without a file_name".to_string()),
+                (Some(file_name), None) => Err(format!("record {:?} without a sha256", file_name)),
+            },
+        )
+        .collect()
+}
+
+/// Compares the content hashes of `current` against `baseline`. The `excluded` file
+/// names, of files that failed or didn't match `--filter`, are left out of both sides.
+fn delta_files(
+    current: &[Metadata],
+    excluded: &[String],
+    baseline: &BTreeMap<String, String>,
+) -> Delta {
+    let current: BTreeMap<&String, &String> = current
+        .iter()
+        .map(|metadata| (&metadata.file_name, &metadata.sha256))
+        .collect();
+    let mut delta = Delta::default();
+    for (file_name, hash) in &current {
+        match baseline.get(*file_name) {
+            None => delta.added.push(file_name.to_string()),
+            Some(old) if old != *hash => delta.changed.push(file_name.to_string()),
+            Some(_) => {}
+        }
+    }
+    delta.removed = baseline
+        .keys()
+        .filter(|file_name| !current.contains_key(file_name) && !excluded.contains(file_name))
+        .cloned()
+        .collect();
+    delta
+}
+
+fn format_delta(delta: &Delta, format: Format) -> String {
+    serialize_report(delta, format).unwrap_or_else(|| {
+        [
+            format_file_list("added", &delta.added),
+            format_file_list("removed", &delta.removed),
+            format_file_list("changed", &delta.changed),
+        ]
+        .join("\n")
+    })
+}
+
+/// Extracts one directory entry and returns where to write its sidecar along with its
+/// metadata, unless `--filter` leaves it out.
+fn process_file(
+    path: &Path,
+    root: &Path,
+    format: Format,
+    options: &ExtractOptions,
+    dir_options: &DirectoryOptions,
+    verbose: bool,
+) -> Result<Option<(PathBuf, Metadata)>, ExtractError> {
+    if verbose {
+        eprintln!("Found SWF file: {:?}", path);
+    }
+
+    // Generate output filename
+    let output_path = sidecar_path(path, root, format, dir_options);
+    // Extract metadata
+    let metadata = load_metadata(path, options)?;
+    if !matches_filter(&metadata, dir_options.filter.as_ref(), verbose) {
+        return Ok(None);
+    }
+    Ok(Some((output_path, metadata)))
+}
+
+/// Whether `metadata` passes `--filter`, reporting in verbose mode a file it leaves out.
+fn matches_filter(metadata: &Metadata, filter: Option<&Filter>, verbose: bool) -> bool {
+    let matches = filter.is_none_or(|filter| filter.matches(metadata));
+    if !matches && verbose {
+        eprintln!("Skipping {:?}: does not match --filter", metadata.file_name);
+    }
+    matches
+}
+
+/// Flags that only act on a directory input
+const DIRECTORY_ONLY_ARGS: [&str; 11] = [
+    "on_error",
+    "glob",
+    "since_hash",
+    "hash_tree",
+    "timeout_total_ms",
+    "parallel_io",
+    "summary",
+    "group_by",
+    "delta_against",
+    "emit_empty_aggregate",
+    "summary_only",
+];
+
+/// Rejects directory-only flags given for a file or standard input, which would
+/// otherwise be ignored.
+fn check_directory_only_args(matches: &clap::ArgMatches) -> Result<(), clap::Error> {
+    match DIRECTORY_ONLY_ARGS
+        .iter()
+        .find(|id| matches.value_source(id) == Some(clap::parser::ValueSource::CommandLine))
+    {
+        Some(id) => Err(Args::command().error(
+            clap::error::ErrorKind::ArgumentConflict,
+            format!(
+                "--{} only applies to a directory input",
+                id.replace('_', "-")
+            ),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Where the sidecar for `path`, found under the directory `root`, is written.
+///
+/// With `--output-dir`, `path`'s location relative to `root` is recreated under the
+/// output directory, so same-named files in different subdirectories don't collide.
+fn sidecar_path(
+    path: &Path,
+    root: &Path,
+    format: Format,
+    dir_options: &DirectoryOptions,
+) -> PathBuf {
+    let template = dir_options.output_template.as_deref();
+    match &dir_options.output_dir {
+        Some(output_dir) => {
+            let relative = path.strip_prefix(root).unwrap_or(path);
+            output_path(&output_dir.join(relative), format, template)
+        }
+        None => output_path(path, format, template),
+    }
+}
+
+/// Whether `sidecar` exists and was written no earlier than `input` was last modified.
+fn sidecar_is_current(sidecar: &Path, input: &Path) -> bool {
+    let modified = |path: &Path| metadata(path).and_then(|meta| meta.modified());
+    match (modified(sidecar), modified(input)) {
+        (Ok(sidecar), Ok(input)) => sidecar >= input,
+        _ => false,
+    }
+}
+
+/// Drops the files `--skip-existing` leaves alone, reporting in verbose mode how many
+/// were skipped.
+fn without_current_sidecars(
+    files: Vec<PathBuf>,
+    root: &Path,
+    format: Format,
+    dir_options: &DirectoryOptions,
+    verbose: bool,
+) -> Vec<PathBuf> {
+    let total = files.len();
+    let remaining: Vec<PathBuf> = files
+        .into_iter()
+        .filter(|path| !sidecar_is_current(&sidecar_path(path, root, format, dir_options), path))
+        .collect();
+    if verbose {
+        eprintln!(
+            "Skipping {} SWF file(s) with up-to-date sidecars, processing {}",
+            total - remaining.len(),
+            remaining.len()
+        );
+    }
+    remaining
+}
+
+/// Reports a directory entry that failed to extract or exceeded `--max-output-bytes`,
+/// returning the placeholder sidecar `--on-error` wants written for it, if any. Ending
+/// the run for `--on-error stop` is up to the caller.
+fn handle_failure(
+    path: &Path,
+    root: &Path,
+    error: &dyn std::fmt::Display,
+    format: Format,
+    dir_options: &DirectoryOptions,
+) -> Option<(PathBuf, String)> {
+    eprintln!(
+        "Error: Failed to extract metadata from {:?}: {}",
+        path, error
+    );
+    match dir_options.on_error {
+        OnError::Skip | OnError::Stop => None,
+        OnError::Placeholder => {
+            let output_path = sidecar_path(path, root, format, dir_options);
+            let file_name = path.to_string_lossy();
+            let placeholder = if format == Format::Csv {
+                csv_error_row(&file_name, &dir_options.format_options)
+            } else {
+                format_error_placeholder(
+                    &file_name,
+                    &error.to_string(),
+                    format,
+                    &dir_options.format_options,
+                )
+            };
+            Some((output_path, placeholder))
+        }
+    }
+}
+
+/// Runs `extract` on a pool of parse workers, one per available CPU, that hand their
+/// sidecars to `write` on a single writer thread, overlapping parsing with disk writes.
+/// `write` also receives each file's index in `files`.
+/// Returns how many files were completed before `stop` tripped, and how many of those
+/// failed.
+fn process_files_pipelined(
+    files: &[PathBuf],
+    extract: impl Fn(&Path) -> (Option<(PathBuf, String)>, bool) + Sync,
+    stop: impl Fn() -> bool + Sync,
+    mut write: impl FnMut(usize, PathBuf, String) + Send,
+) -> (usize, usize) {
+    let workers = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let next = AtomicUsize::new(0);
+    let completed = AtomicUsize::new(0);
+    let failed = AtomicUsize::new(0);
+    let (sender, receiver) = std::sync::mpsc::channel::<(usize, PathBuf, String)>();
+
+    std::thread::scope(|scope| {
+        scope.spawn(move || {
+            for (index, output_path, content) in receiver {
+                write(index, output_path, content);
+            }
+        });
+        for _ in 0..workers {
+            let sender = sender.clone();
+            let (next, completed, failed) = (&next, &completed, &failed);
+            let (extract, stop) = (&extract, &stop);
+            scope.spawn(move || {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    // Only consulted while files remain, so a deadline passing after the
+                    // last file isn't mistaken for a timeout
+                    let Some(path) = files.get(index).filter(|_| !stop()) else {
+                        break;
+                    };
+                    let (sidecar, file_failed) = extract(path);
+                    if file_failed {
+                        failed.fetch_add(1, Ordering::Relaxed);
+                    }
+                    if let Some((output_path, content)) = sidecar {
+                        sender.send((index, output_path, content)).unwrap();
+                    }
+                    completed.fetch_add(1, Ordering::Relaxed);
+                }
+            });
+        }
+        // The writer stops once every worker has dropped its sender
+        drop(sender);
+    });
+    (completed.into_inner(), failed.into_inner())
+}
+
+/// Reads a `sha256sum` manifest into a map from each listed path to its hex digest.
+/// Both the text (`<hash>  <path>`) and binary (`<hash> *<path>`) line forms are accepted,
+/// and a leading `./` on the path is ignored.
+fn load_hash_manifest(path: &Path) -> Result<BTreeMap<PathBuf, String>, String> {
+    let contents = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+    let mut hashes = BTreeMap::new();
+    for (number, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (hash, file) = line
+            .split_once(' ')
+            .ok_or_else(|| format!("line {}: expected `<hash>  <path>`", number + 1))?;
+        let file = file.strip_prefix([' ', '*']).unwrap_or(file);
+        let file = file.strip_prefix("./").unwrap_or(file);
+        hashes.insert(PathBuf::from(file), hash.to_ascii_lowercase());
+    }
+    Ok(hashes)
+}
+
+/// Drops the files whose content still hashes to their `--since-hash` manifest entry.
+/// Files missing from the manifest, or that can't be read, are kept.
+fn changed_files(
+    files: Vec<PathBuf>,
+    dir_path: &Path,
+    hashes: &BTreeMap<PathBuf, String>,
+    verbose: bool,
+) -> Vec<PathBuf> {
+    let total = files.len();
+    let changed: Vec<PathBuf> = files
+        .into_iter()
+        .filter(|path| {
+            let relative = path.strip_prefix(dir_path).unwrap_or(path);
+            let Some(previous) = hashes.get(relative) else {
+                return true;
+            };
+            std::fs::read(path).map_or(true, |contents| {
+                to_hex(&Sha256::digest(&contents)) != *previous
+            })
+        })
+        .collect();
+    if verbose {
+        eprintln!(
+            "Skipping {} of {} SWF file(s) unchanged since --since-hash",
+            total - changed.len(),
+            total
+        );
+    }
+    changed
+}
+
+/// Computes a SHA-256 Merkle root over the content hashes of `files`.
+///
+/// Leaves are ordered by path relative to `dir_path`, so the same archive yields the
+/// same root wherever it lives and however the filesystem orders the entries. Pairs
+/// are hashed together level by level; an odd node out is carried up unchanged. Fails
+/// with the first file that can't be read.
+fn archive_root_hash(dir_path: &Path, files: &[PathBuf]) -> std::io::Result<String> {
+    let mut leaves: Vec<(&Path, Vec<u8>)> = files
+        .iter()
+        .map(|path| {
+            let contents = std::fs::read(path).map_err(|error| {
+                std::io::Error::new(error.kind(), format!("{:?}: {}", path, error))
+            })?;
+            let relative = path.strip_prefix(dir_path).unwrap_or(path);
+            Ok((relative, Sha256::digest(&contents).to_vec()))
+        })
+        .collect::<std::io::Result<_>>()?;
+    leaves.sort();
+
+    let mut level: Vec<Vec<u8>> = leaves.into_iter().map(|(_, hash)| hash).collect();
+    if level.is_empty() {
+        level.push(Sha256::digest([]).to_vec());
+    }
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => Sha256::new()
+                    .chain_update(left)
+                    .chain_update(right)
+                    .finalize()
+                    .to_vec(),
+                [single] => single.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    Ok(to_hex(&level[0]))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Exits with an error if any path doesn't start with `--trim-prefix`, unless `lenient`.
+///
+/// Checking before any extraction happens avoids leaving a half-written set of sidecars.
+fn check_trim_prefix(paths: &[PathBuf], options: &ExtractOptions, lenient: bool) {
+    if let Some(prefix) = &options.trim_prefix
+        && !lenient
+        && let Some(path) = paths.iter().find(|path| !path.starts_with(prefix))
+    {
+        eprintln!(
+            "Error: {:?} does not start with --trim-prefix {:?} (use --lenient to keep it as is)",
+            path, prefix
+        );
+        std::process::exit(1);
+    }
+}
+
+/// A boolean expression over numeric metadata fields, as given to `--filter`
+#[derive(Clone, Debug, PartialEq)]
+enum Filter {
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    Compare(FilterOperand, Comparison, FilterOperand),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum FilterOperand {
+    Width,
+    Height,
+    Frames,
+    FrameRate,
+    Number(f64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Filter {
+    /// Parses expressions like `frames > 100 && (width == 800 || !(height < 600))`.
+    fn parse(expression: &str) -> Result<Filter, String> {
+        let tokens = tokenize_filter(expression)?;
+        let mut parser = FilterParser {
+            tokens: &tokens,
+            pos: 0,
+        };
+        let filter = parser.or()?;
+        match parser.next() {
+            None => Ok(filter),
+            Some(token) => Err(format!("unexpected `{}`", token)),
+        }
+    }
+
+    fn matches(&self, metadata: &Metadata) -> bool {
+        match self {
+            Filter::And(left, right) => left.matches(metadata) && right.matches(metadata),
+            Filter::Or(left, right) => left.matches(metadata) || right.matches(metadata),
+            Filter::Not(filter) => !filter.matches(metadata),
+            Filter::Compare(left, comparison, right) => {
+                let (left, right) = (left.value(metadata), right.value(metadata));
+                match comparison {
+                    Comparison::Eq => left == right,
+                    Comparison::Ne => left != right,
+                    Comparison::Lt => left < right,
+                    Comparison::Le => left <= right,
+                    Comparison::Gt => left > right,
+                    Comparison::Ge => left >= right,
+                }
+            }
+        }
+    }
+}
+
+impl FilterOperand {
+    fn value(self, metadata: &Metadata) -> f64 {
+        match self {
+            FilterOperand::Width => metadata.stage_size.0 as f64,
+            FilterOperand::Height => metadata.stage_size.1 as f64,
+            FilterOperand::Frames => metadata.no_of_frames as f64,
+            FilterOperand::FrameRate => metadata.frame_rate as f64,
+            FilterOperand::Number(value) => value,
+        }
+    }
+}
+
+fn tokenize_filter(expression: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+            let mut token = String::new();
+            while let Some(&c) = chars.peek()
+                && (c.is_ascii_alphanumeric() || c == '_' || c == '.')
+            {
+                token.push(c);
+                chars.next();
+            }
+            tokens.push(token);
+        } else if "&|=!<>()".contains(c) {
+            chars.next();
+            let pair: String = [c, chars.peek().copied().unwrap_or(' ')].iter().collect();
+            if ["&&", "||", "==", "!=", "<=", ">="].contains(&pair.as_str()) {
+                chars.next();
+                tokens.push(pair);
+            } else if "!<>()".contains(c) {
+                tokens.push(c.to_string());
+            } else {
+                return Err(format!("unexpected `{}`", c));
+            }
+        } else {
+            return Err(format!("unexpected `{}`", c));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser for `--filter`; `||` binds looser than `&&`, which binds
+/// looser than `!`.
+struct FilterParser<'a> {
+    tokens: &'a [String],
+    pos: usize,
+}
+
+impl<'a> FilterParser<'a> {
+    fn next(&mut self) -> Option<&'a str> {
+        let token = self.tokens.get(self.pos)?;
+        self.pos += 1;
+        Some(token)
+    }
+
+    fn eat(&mut self, expected: &str) -> bool {
+        let found = self
+            .tokens
+            .get(self.pos)
+            .is_some_and(|token| token == expected);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn or(&mut self) -> Result<Filter, String> {
+        let mut filter = self.and()?;
+        while self.eat("||") {
+            filter = Filter::Or(Box::new(filter), Box::new(self.and()?));
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter, String> {
+        let mut filter = self.unary()?;
+        while self.eat("&&") {
+            filter = Filter::And(Box::new(filter), Box::new(self.unary()?));
+        }
+        Ok(filter)
+    }
+
+    fn unary(&mut self) -> Result<Filter, String> {
+        if self.eat("!") {
+            Ok(Filter::Not(Box::new(self.unary()?)))
+        } else if self.eat("(") {
+            let filter = self.or()?;
+            if !self.eat(")") {
+                return Err("expected `)`".to_string());
+            }
+            Ok(filter)
+        } else {
+            let left = self.operand()?;
+            let comparison = match self.next() {
+                Some("==") => Comparison::Eq,
+                Some("!=") => Comparison::Ne,
+                Some("<") => Comparison::Lt,
+                Some("<=") => Comparison::Le,
+                Some(">") => Comparison::Gt,
+                Some(">=") => Comparison::Ge,
+                Some(token) => return Err(format!("expected a comparison, found `{}`", token)),
+                None => return Err("expected a comparison".to_string()),
+            };
+            Ok(Filter::Compare(left, comparison, self.operand()?))
+        }
+    }
+
+    fn operand(&mut self) -> Result<FilterOperand, String> {
+        match self.next() {
+            Some("width") => Ok(FilterOperand::Width),
+            Some("height") => Ok(FilterOperand::Height),
+            Some("frames") => Ok(FilterOperand::Frames),
+            Some("frame_rate") => Ok(FilterOperand::FrameRate),
+            Some(token) if token.starts_with(|c: char| c.is_ascii_digit() || c == '.') => token
+                .parse()
+                .map(FilterOperand::Number)
+                .map_err(|_| format!("invalid number `{}`", token)),
+            Some(token) => Err(format!(
+                "unknown field `{}` (expected width, height, frames or frame_rate)",
+                token
+            )),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+}
+
+/// Recursively collects SWF files under `dir_path`, in filesystem order unless
+/// `deterministic_order` asks for the paths to be sorted lexicographically.
+fn find_swf_files(dir_path: &PathBuf, deterministic_order: bool) -> Vec<PathBuf> {
+    let mut swf_files = Vec::new();
+
+    for entry in WalkDir::new(dir_path).into_iter().filter_map(|e| e.ok()) {
+        let path = entry.path();
+
+        // Check if file has .swf extension (case-insensitive)
+        if path.is_file()
+            && let Some(ext) = path.extension()
+            && ext.to_str().unwrap_or("").to_lowercase() == "swf"
+        {
+            swf_files.push(path.to_path_buf());
+        }
+    }
+
+    if deterministic_order {
+        swf_files.sort();
+    }
+    swf_files
+}
+
+/// Collects the files a run over `path` processes: in a directory, those matching
+/// `glob` if one is given and every SWF file otherwise; a single file is taken as is.
+fn discover_files(
+    path: &PathBuf,
+    glob: Option<&Glob>,
+    deterministic_order: bool,
+    verbose: bool,
+) -> Vec<PathBuf> {
+    match glob {
+        Some(glob) if path.is_dir() => find_glob_files(path, glob, deterministic_order, verbose),
+        _ => find_swf_files(path, deterministic_order),
+    }
+}
+
+/// Recursively collects the files under `dir_path` whose path relative to it matches
+/// `glob`, whatever their extension, reporting in verbose mode how many were skipped.
+fn find_glob_files(
+    dir_path: &Path,
+    glob: &Glob,
+    deterministic_order: bool,
+    verbose: bool,
+) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut skipped = 0;
+    for entry in WalkDir::new(dir_path).into_iter().filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        if glob.matches(path.strip_prefix(dir_path).unwrap_or(path)) {
+            files.push(path.to_path_buf());
+        } else {
+            skipped += 1;
+        }
+    }
+
+    if deterministic_order {
+        files.sort();
+    }
+    if verbose {
+        eprintln!(
+            "--glob matched {} file(s) and skipped {}",
+            files.len(),
+            skipped
+        );
+    }
+    files
+}
 
-    format_metadata(&metadata, format)
+/// A `--glob` pattern over `/`-separated paths relative to the input directory. `*` and
+/// `?` match within one path component, and a `**` component matches any number of
+/// directories. Matching is case-sensitive.
+#[derive(Clone, Debug, PartialEq)]
+struct Glob {
+    components: Vec<Vec<char>>,
 }
 
-fn format_metadata(metadata: &Metadata, format: &str) -> String {
-    if format == "json" {
-        serde_json::to_string(metadata).unwrap()
-    } else if format == "yaml" {
-        serde_yaml::to_string(metadata).unwrap()
-    } else {
-        format!(
-            "File: {}\nStage Size: {:?}\nNumber of Frames: {}\nFrame Rate: {}",
-            metadata.file_name, metadata.stage_size, metadata.no_of_frames, metadata.frame_rate
-        )
+impl Glob {
+    fn parse(pattern: &str) -> Result<Glob, String> {
+        if pattern.starts_with('/') {
+            return Err("the pattern must be relative to the input directory".to_string());
+        }
+        let components: Vec<Vec<char>> = pattern
+            .split('/')
+            .filter(|component| !component.is_empty() && *component != ".")
+            .map(|component| component.chars().collect())
+            .collect();
+        if components.is_empty() {
+            return Err("empty pattern".to_string());
+        }
+        Ok(Glob { components })
     }
-}
 
-fn process_directory(dir_path: &PathBuf, format: &str, verbose: bool) {
-    let mut swf_count = 0;
+    fn matches(&self, relative_path: &Path) -> bool {
+        let components: Vec<Vec<char>> = relative_path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().chars().collect())
+            .collect();
+        glob_match_components(&self.components, &components)
+    }
+}
 
-    for entry in WalkDir::new(dir_path).into_iter().filter_map(|e| e.ok()) {
-        let path = entry.path();
+fn glob_match_components(pattern: &[Vec<char>], path: &[Vec<char>]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((first, rest)) if first[..] == ['*', '*'] => {
+            (0..=path.len()).any(|skip| glob_match_components(rest, &path[skip..]))
+        }
+        Some((first, rest)) => path.split_first().is_some_and(|(component, path)| {
+            glob_match_component(first, component) && glob_match_components(rest, path)
+        }),
+    }
+}
 
-        // Check if file has .swf extension (case-insensitive)
-        if path.is_file() {
-            if let Some(ext) = path.extension() {
-                if ext.to_str().unwrap_or("").to_lowercase() == "swf" {
-                    swf_count += 1;
+fn glob_match_component(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len()).any(|skip| glob_match_component(rest, &text[skip..])),
+        Some(('?', rest)) => !text.is_empty() && glob_match_component(rest, &text[1..]),
+        Some((c, rest)) => text.first() == Some(c) && glob_match_component(rest, &text[1..]),
+    }
+}
 
-                    if verbose {
-                        eprintln!("Found SWF file: {:?}", path);
-                    }
+/// Counts the SWF files a run over `path` would process; `path` may be a single file.
+fn count_swf_files(path: &PathBuf, glob: Option<&Glob>) -> usize {
+    discover_files(path, glob, false, false).len()
+}
 
-                    // Extract metadata
-                    match std::panic::catch_unwind(|| {
-                        extract_metadata(path.to_str().unwrap(), format)
-                    }) {
-                        Ok(output) => {
-                            // Generate output filename
-                            let output_path = path.with_extension(format!("swf.{}", format));
-                            save_metadata(&output_path, &output, verbose);
-                        }
-                        Err(_) => {
-                            eprintln!("Error: Failed to extract metadata from {:?}", path);
-                        }
-                    }
-                }
+/// Parses each file without extracting anything and returns how many failed; failing
+/// paths are only printed when `verbose`.
+fn validate_files(files: &[PathBuf], verbose: bool) -> usize {
+    let mut invalid = 0;
+    for path in files {
+        if let Err(error) = validate_swf(path) {
+            invalid += 1;
+            if verbose {
+                eprintln!("Invalid SWF {:?}: {}", path, error);
             }
         }
     }
+    invalid
+}
 
-    if verbose {
-        eprintln!("Processed {} SWF file(s)", swf_count);
+const OUTPUT_TEMPLATE_PLACEHOLDERS: [&str; 4] = ["{dir}", "{stem}", "{name}", "{format}"];
+
+/// Checks that an `--output-template` only uses known placeholders.
+fn parse_output_template(template: &str) -> Result<String, String> {
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| "unclosed `{`".to_string())?;
+        let placeholder = &rest[start..start + end + 1];
+        if !OUTPUT_TEMPLATE_PLACEHOLDERS.contains(&placeholder) {
+            return Err(format!(
+                "unknown placeholder `{}` (expected {})",
+                placeholder,
+                OUTPUT_TEMPLATE_PLACEHOLDERS.join(", ")
+            ));
+        }
+        rest = &rest[start + end + 1..];
     }
+    Ok(template.to_string())
 }
 
-fn save_metadata(output_path: &PathBuf, content: &str, verbose: bool) {
+/// Returns where the sidecar for `path` goes: `<path>.<format>` by default, or the
+/// expansion of `template`, where `{dir}` is the SWF's directory, `{stem}` its file
+/// name without extension and `{name}` its full file name.
+fn output_path(path: &Path, format: Format, template: Option<&str>) -> PathBuf {
+    let Some(template) = template else {
+        return path.with_extension(format!("swf.{}", format.name()));
+    };
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_string_lossy(),
+        _ => ".".into(),
+    };
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    PathBuf::from(
+        template
+            .replace("{dir}", &dir)
+            .replace("{stem}", &stem)
+            .replace("{name}", &name)
+            .replace("{format}", format.name()),
+    )
+}
+
+/// Writes a sidecar and, with `checksum`, a `<sidecar>.sha256` file beside it in
+/// `sha256sum` format so the catalog can be checked with `sha256sum -c`.
+fn save_metadata(output_path: &PathBuf, content: &str, checksum: bool, verbose: bool) {
+    // Templated output paths may point into directories that don't exist yet
+    if let Some(parent) = output_path.parent()
+        && !parent.as_os_str().is_empty()
+        && let Err(e) = std::fs::create_dir_all(parent)
+    {
+        eprintln!("Error creating directory {:?}: {}", parent, e);
+        return;
+    }
     match File::create(output_path) {
         Ok(mut file) => match file.write_all(content.as_bytes()) {
             Ok(_) => {
                 if verbose {
                     eprintln!("Saved metadata to: {:?}", output_path);
                 }
+                if checksum {
+                    save_checksum(output_path, content);
+                }
             }
             Err(e) => {
                 eprintln!("Error writing to {:?}: {}", output_path, e);
@@ -154,62 +1870,42 @@ fn save_metadata(output_path: &PathBuf, content: &str, verbose: bool) {
     }
 }
 
+fn save_checksum(output_path: &Path, content: &str) {
+    let mut checksum_path = output_path.as_os_str().to_owned();
+    checksum_path.push(".sha256");
+    let name = output_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let line = format!("{}  {}\n", to_hex(&Sha256::digest(content)), name);
+    if let Err(e) = std::fs::write(&checksum_path, line) {
+        eprintln!("Error writing to {:?}: {}", checksum_path, e);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    // Helper function to create test metadata
-    fn create_test_metadata() -> Metadata {
-        Metadata {
-            file_name: "test.swf".to_string(),
-            stage_size: (800, 600),
-            no_of_frames: 100,
-            frame_rate: 30,
-        }
-    }
-
-    #[test]
-    fn test_metadata_to_json() {
-        let metadata = create_test_metadata();
-        let result = format_metadata(&metadata, "json");
-
-        // Verify it's valid JSON
-        assert!(result.contains("\"file_name\":\"test.swf\""));
-        assert!(result.contains("\"stage_size\":[800,600]"));
-        assert!(result.contains("\"no_of_frames\":100"));
-        assert!(result.contains("\"frame_rate\":30"));
-
-        // Verify it can be parsed back
-        let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
-        assert_eq!(parsed["file_name"], "test.swf");
-        assert_eq!(parsed["stage_size"][0], 800);
-        assert_eq!(parsed["stage_size"][1], 600);
-    }
-
-    #[test]
-    fn test_metadata_to_yaml() {
-        let metadata = create_test_metadata();
-        let result = format_metadata(&metadata, "yaml");
-
-        // Verify YAML format
-        assert!(result.contains("file_name: test.swf"));
-        assert!(result.contains("stage_size:"));
-        assert!(result.contains("- 800"));
-        assert!(result.contains("- 600"));
-        assert!(result.contains("no_of_frames: 100"));
-        assert!(result.contains("frame_rate: 30"));
-    }
-
-    #[test]
-    fn test_metadata_to_text() {
-        let metadata = create_test_metadata();
-        let result = format_metadata(&metadata, "text");
-
-        // Verify text format
-        assert!(result.contains("File: test.swf"));
-        assert!(result.contains("Stage Size: (800, 600)"));
-        assert!(result.contains("Number of Frames: 100"));
-        assert!(result.contains("Frame Rate: 30"));
+    // The binary tests only tell SWFs apart by their header, so each one is an
+    // uncompressed 800x600, 100-frame movie at 30fps with `edit` applied to its header
+    fn create_test_swf(edit: impl FnOnce(&mut swf::Header), tags: &[swf::Tag]) -> Vec<u8> {
+        let mut header = swf::Header {
+            compression: swf::Compression::None,
+            version: 10,
+            stage_size: swf::Rectangle {
+                x_min: swf::Twips::ZERO,
+                x_max: swf::Twips::from_pixels(800.0),
+                y_min: swf::Twips::ZERO,
+                y_max: swf::Twips::from_pixels(600.0),
+            },
+            frame_rate: swf::Fixed8::from_f32(30.0),
+            num_frames: 100,
+        };
+        edit(&mut header);
+        let mut data = Vec::new();
+        swf::write_swf(&header, tags, &mut data).unwrap();
+        data
     }
 
     #[test]
@@ -241,27 +1937,56 @@ mod tests {
     }
 
     #[test]
-    fn test_metadata_equality() {
-        let metadata1 = create_test_metadata();
-        let metadata2 = Metadata {
-            file_name: "test.swf".to_string(),
-            stage_size: (800, 600),
-            no_of_frames: 100,
-            frame_rate: 30,
-        };
-        assert_eq!(metadata1, metadata2);
+    fn test_output_template_expansion() {
+        let input = Path::new("archive/games/level1.swf");
+        assert_eq!(
+            output_path(input, Format::Json, Some("{dir}/meta/{stem}.{format}")),
+            PathBuf::from("archive/games/meta/level1.json")
+        );
+        assert_eq!(
+            output_path(input, Format::Yaml, Some("out/{name}.{format}")),
+            PathBuf::from("out/level1.swf.yaml")
+        );
+        assert_eq!(
+            output_path(input, Format::Json, None),
+            PathBuf::from("archive/games/level1.swf.json")
+        );
+        assert!(parse_output_template("{dir}/{stem}.{format}").is_ok());
+        assert!(parse_output_template("{dir}/{hash}.json").is_err());
     }
 
     #[test]
-    fn test_metadata_inequality() {
-        let metadata1 = create_test_metadata();
-        let metadata2 = Metadata {
-            file_name: "different.swf".to_string(),
-            stage_size: (800, 600),
-            no_of_frames: 100,
-            frame_rate: 30,
+    fn test_directory_csv_is_combined() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.swf", "b.swf", "c\nd.swf"] {
+            let data = create_test_swf(|_| {}, &[swf::Tag::ShowFrame]);
+            std::fs::write(dir.path().join(name), data).unwrap();
+        }
+        let options = ExtractOptions {
+            trim_prefix: Some(dir.path().to_path_buf()),
+            ..Default::default()
         };
-        assert_ne!(metadata1, metadata2);
+        let dir_options = DirectoryOptions {
+            deterministic_order: true,
+            ..Default::default()
+        };
+        process_directory(
+            &dir.path().to_path_buf(),
+            Format::Csv,
+            &options,
+            &dir_options,
+            false,
+        );
+
+        assert!(!dir.path().join("a.swf.csv").exists());
+        let csv = std::fs::read_to_string(dir.path().join(COMBINED_CSV_NAME)).unwrap();
+        assert_eq!(
+            csv,
+            "file_name,stage_width,stage_height,no_of_frames,frame_rate\n\
+             a.swf,800,600,100,30\n\
+             b.swf,800,600,100,30\n\
+             \"c\nd.swf\",800,600,100,30"
+        );
     }
 
     #[test]
@@ -286,12 +2011,1078 @@ mod tests {
     }
 
     #[test]
-    fn test_format_metadata_default_to_text() {
-        let metadata = create_test_metadata();
-        let result = format_metadata(&metadata, "unknown_format");
+    fn test_deterministic_order_is_lexicographic() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("a")).unwrap();
+        for name in ["b.swf", "a/z.swf", "A.SWF", "notes.txt"] {
+            std::fs::write(dir.path().join(name), b"").unwrap();
+        }
+
+        let files = find_swf_files(&dir.path().to_path_buf(), true);
+        let names: Vec<_> = files
+            .iter()
+            .map(|p| p.strip_prefix(dir.path()).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                PathBuf::from("A.SWF"),
+                PathBuf::from("a/z.swf"),
+                PathBuf::from("b.swf")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_directory_summary_counts_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = create_test_swf(|_| {}, &[swf::Tag::ShowFrame]);
+        std::fs::write(dir.path().join("good.swf"), data).unwrap();
+        std::fs::write(dir.path().join("corrupt.swf"), b"not a swf file").unwrap();
+
+        let summary = process_directory(
+            &dir.path().to_path_buf(),
+            Format::Json,
+            &ExtractOptions::default(),
+            &DirectoryOptions::default(),
+            false,
+        );
+        assert_eq!(
+            summary,
+            DirectorySummary {
+                completed: 2,
+                failed: 1,
+                timed_out: false,
+                stub_found: false,
+                hash_failed: false,
+            }
+        );
+        assert!(dir.path().join("good.swf.json").exists());
+        assert!(!dir.path().join("corrupt.swf.json").exists());
+    }
+
+    // Helper function to create a one-frame movie whose only script is
+    // loadMovieNum("main.swf", 0), which `is_preloader` flags as a stub
+    fn create_preloader_stub() -> Vec<u8> {
+        use swf::avm1::types::{Action, GetUrl};
+
+        let mut load_main = Vec::new();
+        let mut writer = swf::avm1::write::Writer::new(&mut load_main, 10);
+        writer
+            .write_action(&Action::GetUrl(GetUrl {
+                url: "main.swf".into(),
+                target: "_level0".into(),
+            }))
+            .unwrap();
+        writer.write_action(&Action::End).unwrap();
+        create_test_swf(
+            |header| {
+                header.stage_size.x_max = swf::Twips::from_pixels(550.0);
+                header.stage_size.y_max = swf::Twips::from_pixels(400.0);
+                header.num_frames = 1;
+            },
+            &[swf::Tag::DoAction(&load_main), swf::Tag::ShowFrame],
+        )
+    }
+
+    #[test]
+    fn test_stop_on_stub_halts_directory_run() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a_stub.swf"), create_preloader_stub()).unwrap();
+        let data = create_test_swf(|_| {}, &[swf::Tag::ShowFrame]);
+        std::fs::write(dir.path().join("b_movie.swf"), data).unwrap();
+
+        let options = ExtractOptions {
+            scripts: true,
+            ..Default::default()
+        };
+        let dir_options = DirectoryOptions {
+            deterministic_order: true,
+            stop_on_stub: true,
+            ..Default::default()
+        };
+        let summary = process_directory(
+            &dir.path().to_path_buf(),
+            Format::Json,
+            &options,
+            &dir_options,
+            false,
+        );
+        assert_eq!(
+            summary,
+            DirectorySummary {
+                completed: 1,
+                failed: 0,
+                timed_out: false,
+                stub_found: true,
+                hash_failed: false,
+            }
+        );
+        assert!(!dir.path().join("a_stub.swf.json").exists());
+        assert!(!dir.path().join("b_movie.swf.json").exists());
+        // The heuristic only runs with --scripts
+        assert!(Args::try_parse_from(["extract-metadata", "-i", "dir", "--stop-on-stub"]).is_err());
+    }
+
+    #[test]
+    fn test_exclude_empty_skips_stub_sidecars() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("stub.swf"), create_preloader_stub()).unwrap();
+        let data = create_test_swf(|_| {}, &[swf::Tag::ShowFrame]);
+        std::fs::write(dir.path().join("movie.swf"), data).unwrap();
+
+        let options = ExtractOptions {
+            scripts: true,
+            ..Default::default()
+        };
+        let dir_options = DirectoryOptions {
+            exclude_empty: true,
+            ..Default::default()
+        };
+        let summary = process_directory(
+            &dir.path().to_path_buf(),
+            Format::Json,
+            &options,
+            &dir_options,
+            false,
+        );
+        assert_eq!(summary.failed, 0);
+        assert!(!dir.path().join("stub.swf.json").exists());
+        assert!(dir.path().join("movie.swf.json").exists());
+    }
+
+    #[test]
+    fn test_stdout_combines_directory_results() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, num_frames) in [("a.swf", 1), ("b.swf", 2)] {
+            let data = create_test_swf(
+                |header| header.num_frames = num_frames,
+                &[swf::Tag::ShowFrame],
+            );
+            std::fs::write(dir.path().join(name), data).unwrap();
+        }
+        let dir_options = DirectoryOptions {
+            deterministic_order: true,
+            stdout: true,
+            ..Default::default()
+        };
+        process_directory(
+            &dir.path().to_path_buf(),
+            Format::Json,
+            &ExtractOptions::default(),
+            &dir_options,
+            false,
+        );
+        assert!(!dir.path().join("a.swf.json").exists());
+
+        let metadata = Metadata {
+            file_name: "test.swf".to_string(),
+            ..Default::default()
+        };
+        let outputs = [
+            extract_metadata::format(&metadata, Format::Json),
+            extract_metadata::format(&metadata, Format::Json),
+        ];
+        let options = FormatOptions::default();
+        let parsed: serde_json::Value =
+            serde_json::from_str(&combine_outputs(&outputs, Format::Json, &options)).unwrap();
+        assert_eq!(parsed.as_array().unwrap().len(), 2);
+        assert_eq!(parsed[1]["file_name"], "test.swf");
+        assert_eq!(combine_outputs(&[], Format::Json, &options), "[]");
+
+        let yaml: serde_yaml::Value =
+            serde_yaml::from_str(&combine_outputs(&outputs, Format::Yaml, &options)).unwrap();
+        assert_eq!(yaml.as_sequence().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_invalid_format_is_rejected() {
+        let args = Args::try_parse_from(["extract-metadata", "-i", "a.swf", "-f", "yaml"]).unwrap();
+        assert_eq!(args.format, Format::Yaml);
+        let args = Args::try_parse_from(["extract-metadata", "-i", "a.swf"]).unwrap();
+        assert_eq!(args.format, Format::Json);
+        assert!(Args::try_parse_from(["extract-metadata", "-i", "a.swf", "-f", "jsom"]).is_err());
+    }
+
+    #[test]
+    fn test_format_options_reject_unused_refinements() {
+        let resolve = |extra: &[&str]| {
+            let args =
+                Args::try_parse_from(["extract-metadata", "-i", "a.swf"].iter().chain(extra))
+                    .unwrap();
+            format_options(&args)
+        };
+        assert!(
+            resolve(&["-f", "text", "--compact-text"])
+                .unwrap()
+                .compact_text
+        );
+        assert!(
+            resolve(&["-f", "jsonl", "--json-number-mode", "string"])
+                .unwrap()
+                .string_numbers
+        );
+        assert!(resolve(&["-f", "yaml", "--compact-text"]).is_err());
+        assert!(resolve(&["-f", "csv", "--json-number-mode", "string"]).is_err());
+        assert_eq!(
+            resolve(&["-f", "csv", "--delimiter", ";"])
+                .unwrap()
+                .csv_delimiter,
+            ';'
+        );
+        assert!(resolve(&["-f", "json", "--delimiter", ";"]).is_err());
+        assert!(resolve(&["-f", "csv", "--delimiter", "\""]).is_err());
+    }
+
+    #[test]
+    fn test_directory_only_args_rejected_explicitly() {
+        let check = |extra: &[&str]| {
+            let matches = Args::command()
+                .try_get_matches_from(["extract-metadata", "-i", "a.swf"].iter().chain(extra))
+                .unwrap();
+            check_directory_only_args(&matches)
+        };
+        assert!(check(&[]).is_ok());
+        assert!(check(&["--filter", "frames > 100", "--scripts", "--stop-on-stub"]).is_ok());
+        assert!(check(&["--summary"]).is_err());
+        assert!(check(&["--on-error", "skip"]).is_err());
+        assert!(check(&["--glob", "*.swf"]).is_err());
+    }
+
+    #[test]
+    fn test_stdin_rejects_dereference_imports() {
+        let parse = |extra: &[&str]| {
+            Args::try_parse_from(["extract-metadata", "-i", "-"].iter().chain(extra)).unwrap()
+        };
+        assert!(check_stdin_args(&parse(&[])).is_ok());
+        assert!(check_stdin_args(&parse(&["--dereference-imports"])).is_err());
+    }
+
+    #[test]
+    fn test_keep_single_input_excludes_empty() {
+        let args = Args::try_parse_from([
+            "extract-metadata",
+            "-i",
+            "-",
+            "--scripts",
+            "--exclude-empty",
+        ])
+        .unwrap();
+        let mut metadata = Metadata {
+            file_name: STDIN_FILE_NAME.to_string(),
+            ..Default::default()
+        };
+        assert!(keep_single_input(&metadata, &args, STDIN_FILE_NAME));
+        metadata.is_preloader = Some(true);
+        assert!(!keep_single_input(&metadata, &args, STDIN_FILE_NAME));
+    }
+
+    #[test]
+    fn test_emit_empty_aggregate() {
+        let run = |format: Format, emit_empty_aggregate: bool| {
+            let dir = tempfile::tempdir().unwrap();
+            let dir_options = DirectoryOptions {
+                emit_empty_aggregate,
+                ..Default::default()
+            };
+            process_directory(
+                &dir.path().to_path_buf(),
+                format,
+                &ExtractOptions::default(),
+                &dir_options,
+                false,
+            );
+            std::fs::read_to_string(dir.path().join(format!("metadata.{}", format.name()))).ok()
+        };
+        assert_eq!(run(Format::Json, false), None);
+        assert_eq!(run(Format::Csv, false), None);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&run(Format::Json, true).unwrap()).unwrap();
+        assert_eq!(json, serde_json::json!([]));
+        let yaml: serde_yaml::Value =
+            serde_yaml::from_str(&run(Format::Yaml, true).unwrap()).unwrap();
+        assert_eq!(yaml, serde_yaml::Value::Sequence(Vec::new()));
+        assert_eq!(
+            run(Format::Csv, true).unwrap(),
+            "file_name,stage_width,stage_height,no_of_frames,frame_rate"
+        );
+    }
+
+    #[test]
+    fn test_summary_only_writes_no_sidecars() {
+        let dir = tempfile::tempdir().unwrap();
+        let output_dir = tempfile::tempdir().unwrap();
+        for (name, num_frames) in [("a.swf", 10), ("b.swf", 5)] {
+            let data = create_test_swf(
+                |header| header.num_frames = num_frames,
+                &[swf::Tag::ShowFrame],
+            );
+            std::fs::write(dir.path().join(name), data).unwrap();
+        }
+        for format in [Format::Json, Format::Csv] {
+            let dir_options = DirectoryOptions {
+                output_dir: Some(output_dir.path().to_path_buf()),
+                summary: true,
+                summary_only: true,
+                ..Default::default()
+            };
+            let summary = process_directory(
+                &dir.path().to_path_buf(),
+                format,
+                &ExtractOptions::default(),
+                &dir_options,
+                false,
+            );
+            assert_eq!(summary.completed, 2);
+
+            let written: Vec<_> = std::fs::read_dir(output_dir.path())
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name())
+                .collect();
+            assert_eq!(written, [SUMMARY_NAME]);
+            let summary: serde_json::Value = serde_json::from_str(
+                &std::fs::read_to_string(output_dir.path().join(SUMMARY_NAME)).unwrap(),
+            )
+            .unwrap();
+            assert_eq!(summary["total_frames"], 15);
+        }
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_quiet_conflicts_with_verbose() {
+        assert!(Args::try_parse_from(["extract-metadata", "-i", "a.swf", "--quiet"]).is_ok());
+        assert!(
+            Args::try_parse_from(["extract-metadata", "-i", "a.swf", "--quiet", "--verbose"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_load_stdin_metadata() {
+        let data = create_test_swf(|_| {}, &[swf::Tag::ShowFrame]);
+        let metadata = load_stdin_metadata(&data[..], &ExtractOptions::default()).unwrap();
+        assert_eq!(metadata.file_name, "<stdin>");
+        assert_eq!(metadata.no_of_frames, 100);
+
+        assert!(load_stdin_metadata(&b"not a swf file"[..], &ExtractOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_group_by_stage_size() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, width) in [("a.swf", 550.0), ("b.swf", 800.0), ("c.swf", 550.0)] {
+            let data = create_test_swf(
+                |header| {
+                    header.stage_size.x_max = swf::Twips::from_pixels(width);
+                    header.stage_size.y_max = swf::Twips::from_pixels(400.0);
+                },
+                &[swf::Tag::ShowFrame],
+            );
+            std::fs::write(dir.path().join(name), data).unwrap();
+        }
+        let options = ExtractOptions {
+            trim_prefix: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let metadata: Vec<Metadata> = find_swf_files(&dir.path().to_path_buf(), true)
+            .iter()
+            .map(|path| extract_metadata::extract_with_options(path, &options).unwrap())
+            .collect();
+        let groups = group_files(&metadata, GroupBy::StageSize);
+        assert_eq!(
+            groups.keys().collect::<Vec<_>>(),
+            vec!["550x400", "800x400"]
+        );
+        assert_eq!(
+            groups["550x400"],
+            Group {
+                count: 2,
+                files: vec!["a.swf".to_string(), "c.swf".to_string()],
+            }
+        );
+
+        let parsed: serde_json::Value =
+            serde_json::from_str(&format_groups(&groups, Format::Json)).unwrap();
+        assert_eq!(parsed["800x400"]["count"], 1);
+    }
+
+    #[test]
+    fn test_group_by_honours_on_error() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a_corrupt.swf"), b"not a swf file").unwrap();
+        let data = create_test_swf(|_| {}, &[swf::Tag::ShowFrame]);
+        std::fs::write(dir.path().join("b.swf"), data).unwrap();
+
+        let run = |on_error: OnError| {
+            let dir_options = DirectoryOptions {
+                deterministic_order: true,
+                on_error,
+                stdout: true,
+                group_by: Some(GroupBy::Version),
+                ..Default::default()
+            };
+            process_directory(
+                &dir.path().to_path_buf(),
+                Format::Json,
+                &ExtractOptions::default(),
+                &dir_options,
+                false,
+            )
+        };
+        let summary = run(OnError::Skip);
+        assert_eq!((summary.completed, summary.failed), (2, 1));
+        let summary = run(OnError::Stop);
+        assert_eq!((summary.completed, summary.failed), (1, 1));
+
+        let args = |extra: &[&str]| {
+            Args::try_parse_from(
+                [
+                    "extract-metadata",
+                    "-i",
+                    "dir",
+                    "--stdout",
+                    "--group-by",
+                    "version",
+                ]
+                .iter()
+                .chain(extra),
+            )
+        };
+        assert!(args(&["--max-output-bytes", "10"]).is_err());
+        assert!(check_report_options(&args(&["--on-error", "placeholder"]).unwrap()).is_err());
+        assert!(check_report_options(&args(&["--on-error", "stop"]).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_skip_existing_keeps_current_sidecars() {
+        let dir = tempfile::tempdir().unwrap();
+        let output_dir = tempfile::tempdir().unwrap();
+        for name in ["done.swf", "new.swf"] {
+            let data = create_test_swf(|_| {}, &[swf::Tag::ShowFrame]);
+            std::fs::write(dir.path().join(name), data).unwrap();
+        }
+        // Sidecars are looked up where --output-dir puts them, not next to the SWF
+        std::fs::write(output_dir.path().join("done.swf.json"), "previous run").unwrap();
+        std::fs::write(dir.path().join("new.swf.json"), "previous run").unwrap();
+        let dir_options = DirectoryOptions {
+            output_dir: Some(output_dir.path().to_path_buf()),
+            skip_existing: true,
+            ..Default::default()
+        };
+        process_directory(
+            &dir.path().to_path_buf(),
+            Format::Json,
+            &ExtractOptions::default(),
+            &dir_options,
+            false,
+        );
+
+        let read = |path: PathBuf| std::fs::read_to_string(path).unwrap();
+        assert_eq!(
+            read(output_dir.path().join("done.swf.json")),
+            "previous run"
+        );
+        assert!(read(output_dir.path().join("new.swf.json")).contains("\"file_name\""));
+    }
+
+    #[test]
+    fn test_summary_totals_frames() {
+        let dir = tempfile::tempdir().unwrap();
+        let output_dir = tempfile::tempdir().unwrap();
+        for (name, num_frames, rate) in [("a.swf", 10, 24.0), ("b.swf", 5, 30.0)] {
+            let data = create_test_swf(
+                |header| {
+                    header.num_frames = num_frames;
+                    header.frame_rate = swf::Fixed8::from_f32(rate);
+                },
+                &[swf::Tag::ShowFrame],
+            );
+            std::fs::write(dir.path().join(name), data).unwrap();
+        }
+        std::fs::write(dir.path().join("corrupt.swf"), b"not a swf file").unwrap();
+        let dir_options = DirectoryOptions {
+            output_dir: Some(output_dir.path().to_path_buf()),
+            summary: true,
+            ..Default::default()
+        };
+        process_directory(
+            &dir.path().to_path_buf(),
+            Format::Json,
+            &ExtractOptions::default(),
+            &dir_options,
+            false,
+        );
+
+        let summary: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(output_dir.path().join(SUMMARY_NAME)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(summary["files_processed"], 2);
+        assert_eq!(summary["files_failed"], 1);
+        assert_eq!(summary["total_frames"], 15);
+        assert_eq!(summary["min_frame_rate"], 24.0);
+        assert_eq!(summary["average_frame_rate"], 27.0);
+        assert_eq!(summary["stage_sizes"]["800x600"], 2);
+    }
+
+    #[test]
+    fn test_delta_against_reports_added_file() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.swf", "b.swf"] {
+            let data = create_test_swf(|_| {}, &[swf::Tag::ShowFrame]);
+            std::fs::write(dir.path().join(name), data).unwrap();
+        }
+        let options = ExtractOptions {
+            trim_prefix: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        // The baseline was taken before b.swf was added
+        let previous =
+            extract_metadata::extract_with_options(&dir.path().join("a.swf"), &options).unwrap();
+        let baseline_path = dir.path().join("baseline.json");
+        let baseline = combine_outputs(
+            &[extract_metadata::format(&previous, Format::Json)],
+            Format::Json,
+            &FormatOptions::default(),
+        );
+        std::fs::write(&baseline_path, baseline).unwrap();
+
+        let baseline = load_baseline(&baseline_path).unwrap();
+        let current: Vec<Metadata> = find_swf_files(&dir.path().to_path_buf(), true)
+            .iter()
+            .map(|path| extract_metadata::extract_with_options(path, &options).unwrap())
+            .collect();
+        assert_eq!(
+            delta_files(&current, &[], &baseline),
+            Delta {
+                added: vec!["b.swf".to_string()],
+                removed: vec![],
+                changed: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn test_delta_against_compares_file_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.swf", "b.swf"] {
+            let data = create_test_swf(|_| {}, &[swf::Tag::ShowFrame]);
+            std::fs::write(dir.path().join(name), data).unwrap();
+        }
+        let options = ExtractOptions {
+            trim_prefix: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let files = find_swf_files(&dir.path().to_path_buf(), true);
+        let outputs: Vec<String> = files
+            .iter()
+            .map(|path| {
+                let metadata = extract_metadata::extract_with_options(path, &options).unwrap();
+                extract_metadata::format(&metadata, Format::Json)
+            })
+            .collect();
+        let baseline_path = dir.path().join("baseline.json");
+        let baseline = combine_outputs(&outputs, Format::Json, &FormatOptions::default());
+        std::fs::write(&baseline_path, baseline).unwrap();
+        let baseline = load_baseline(&baseline_path).unwrap();
+
+        // Extra fields in the current run don't count as a change; new bytes in b.swf do
+        let data = create_test_swf(|_| {}, &[swf::Tag::ShowFrame, swf::Tag::ShowFrame]);
+        std::fs::write(dir.path().join("b.swf"), data).unwrap();
+        let current_options = ExtractOptions {
+            tags: true,
+            ..options
+        };
+        let current: Vec<Metadata> = files
+            .iter()
+            .map(|path| extract_metadata::extract_with_options(path, &current_options).unwrap())
+            .collect();
+        let delta = delta_files(&current, &[], &baseline);
+        assert_eq!(delta.changed, vec!["b.swf".to_string()]);
+        assert!(delta.added.is_empty() && delta.removed.is_empty());
+    }
+
+    #[test]
+    fn test_delta_against_covers_whole_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.swf", "b.swf"] {
+            let data = create_test_swf(|_| {}, &[swf::Tag::ShowFrame]);
+            std::fs::write(dir.path().join(name), data).unwrap();
+        }
+        let options = ExtractOptions {
+            trim_prefix: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let discovered = find_swf_files(&dir.path().to_path_buf(), true);
+        let outputs: Vec<String> = discovered
+            .iter()
+            .map(|path| {
+                let metadata = extract_metadata::extract_with_options(path, &options).unwrap();
+                extract_metadata::format(&metadata, Format::Json)
+            })
+            .collect();
+        let baseline_path = dir.path().join("baseline.json");
+        let baseline = combine_outputs(&outputs, Format::Json, &FormatOptions::default());
+        std::fs::write(&baseline_path, baseline).unwrap();
+        // a.swf is unchanged since the manifest, so --since-hash alone would skip it
+        let manifest = dir.path().join("manifest.sha256");
+        let a = std::fs::read(dir.path().join("a.swf")).unwrap();
+        std::fs::write(
+            &manifest,
+            format!("{}  a.swf\n", to_hex(&Sha256::digest(a))),
+        )
+        .unwrap();
+        // b.swf now fails, so it drops out of both sides rather than counting as removed
+        std::fs::write(dir.path().join("b.swf"), b"not a swf file").unwrap();
+
+        let dir_options = DirectoryOptions {
+            deterministic_order: true,
+            since_hashes: Some(load_hash_manifest(&manifest).unwrap()),
+            baseline: Some(load_baseline(&baseline_path).unwrap()),
+            ..Default::default()
+        };
+        let files = files_to_extract(&discovered, dir.path(), &dir_options, false);
+        assert_eq!(files, discovered);
+        let summary = process_directory(
+            &dir.path().to_path_buf(),
+            Format::Json,
+            &options,
+            &dir_options,
+            false,
+        );
+        assert_eq!((summary.completed, summary.failed), (2, 1));
+
+        let current = vec![extract_metadata::extract_with_options(&files[0], &options).unwrap()];
+        let delta = delta_files(
+            &current,
+            &[reported_file_name(&files[1], &options)],
+            dir_options.baseline.as_ref().unwrap(),
+        );
+        assert_eq!(delta, Delta::default());
+    }
+
+    #[test]
+    fn test_on_error_placeholder_writes_error_sidecar() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("corrupt.swf"), b"not a swf file").unwrap();
+
+        let dir_options = DirectoryOptions {
+            on_error: OnError::Placeholder,
+            ..Default::default()
+        };
+        process_directory(
+            &dir.path().to_path_buf(),
+            Format::Json,
+            &ExtractOptions::default(),
+            &dir_options,
+            false,
+        );
+
+        let sidecar = std::fs::read_to_string(dir.path().join("corrupt.swf.json")).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&sidecar).unwrap();
+        assert!(
+            parsed["file_name"]
+                .as_str()
+                .unwrap()
+                .ends_with("corrupt.swf")
+        );
+        assert!(
+            parsed["error"]
+                .as_str()
+                .unwrap()
+                .starts_with("failed to decompress SWF")
+        );
+    }
+
+    #[test]
+    fn test_filter_selects_matching_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, num_frames) in [("long.swf", 150), ("short.swf", 50)] {
+            let data = create_test_swf(
+                |header| header.num_frames = num_frames,
+                &[swf::Tag::ShowFrame],
+            );
+            std::fs::write(dir.path().join(name), data).unwrap();
+        }
+
+        let dir_options = DirectoryOptions {
+            filter: Some(Filter::parse("frames > 100 && width == 800").unwrap()),
+            ..Default::default()
+        };
+        process_directory(
+            &dir.path().to_path_buf(),
+            Format::Json,
+            &ExtractOptions::default(),
+            &dir_options,
+            false,
+        );
+
+        assert!(dir.path().join("long.swf.json").exists());
+        assert!(!dir.path().join("short.swf.json").exists());
+    }
+
+    #[test]
+    fn test_glob_matches_relative_paths() {
+        let glob = Glob::parse("characters/**/*.swf").unwrap();
+        assert!(glob.matches(Path::new("characters/hero.swf")));
+        assert!(glob.matches(Path::new("characters/npc/villager.swf")));
+        assert!(!glob.matches(Path::new("levels/1.swf")));
+        assert!(!glob.matches(Path::new("characters/hero.swf.json")));
+        assert!(
+            Glob::parse("level?.swf")
+                .unwrap()
+                .matches(Path::new("level1.swf"))
+        );
+        assert!(Glob::parse("/abs/*.swf").is_err());
+        assert!(Glob::parse("").is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("characters/npc")).unwrap();
+        std::fs::create_dir_all(dir.path().join("levels")).unwrap();
+        for name in [
+            "characters/hero.swf",
+            "characters/npc/villager.swf",
+            "levels/1.swf",
+        ] {
+            std::fs::write(dir.path().join(name), b"not parsed").unwrap();
+        }
+        let files = find_glob_files(dir.path(), &glob, true, false);
+        assert_eq!(
+            files,
+            vec![
+                dir.path().join("characters/hero.swf"),
+                dir.path().join("characters/npc/villager.swf"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_count_swf_files_parses_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        // Not even valid SWFs: counting must not parse them
+        std::fs::write(dir.path().join("a.swf"), b"not a swf").unwrap();
+        std::fs::write(dir.path().join("B.SWF"), b"not a swf").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), b"ignored").unwrap();
+
+        assert_eq!(count_swf_files(&dir.path().to_path_buf(), None), 2);
+        assert_eq!(count_swf_files(&dir.path().join("a.swf"), None), 1);
+        // --glob scopes the count the same way it scopes a directory run
+        let glob = Glob::parse("*.swf").unwrap();
+        assert_eq!(count_swf_files(&dir.path().to_path_buf(), Some(&glob)), 1);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn test_validate_files_counts_invalid() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("good.swf"),
+            create_test_swf(|_| {}, &[swf::Tag::ShowFrame]),
+        )
+        .unwrap();
+        let clean = find_swf_files(&dir.path().to_path_buf(), true);
+        assert_eq!(validate_files(&clean, false), 0);
+
+        std::fs::write(dir.path().join("corrupt.swf"), b"not a swf file").unwrap();
+        let mixed = find_swf_files(&dir.path().to_path_buf(), true);
+        assert_eq!(validate_files(&mixed, false), 1);
+        assert!(!dir.path().join("good.swf.json").exists());
+    }
+
+    #[test]
+    fn test_timeout_total_stops_early() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.swf", "b.swf"] {
+            std::fs::write(
+                dir.path().join(name),
+                create_test_swf(|_| {}, &[swf::Tag::ShowFrame]),
+            )
+            .unwrap();
+        }
+
+        for parallel_io in [false, true] {
+            let dir_options = DirectoryOptions {
+                timeout_total: Some(Duration::ZERO),
+                parallel_io,
+                ..Default::default()
+            };
+            let summary = process_directory(
+                &dir.path().to_path_buf(),
+                Format::Json,
+                &ExtractOptions::default(),
+                &dir_options,
+                false,
+            );
+
+            assert_eq!(
+                summary,
+                DirectorySummary {
+                    completed: 0,
+                    failed: 0,
+                    timed_out: true,
+                    stub_found: false,
+                    hash_failed: false,
+                }
+            );
+            assert!(!dir.path().join("a.swf.json").exists());
+            assert!(!dir.path().join("b.swf.json").exists());
+        }
+
+        // A generous deadline that never fires isn't reported, even on the pipeline
+        let dir_options = DirectoryOptions {
+            timeout_total: Some(Duration::from_secs(60)),
+            parallel_io: true,
+            ..Default::default()
+        };
+        let summary = process_directory(
+            &dir.path().to_path_buf(),
+            Format::Json,
+            &ExtractOptions::default(),
+            &dir_options,
+            false,
+        );
+        assert!(!summary.timed_out);
+        assert_eq!(summary.completed, 2);
+    }
+
+    #[test]
+    fn test_parallel_io_matches_serial() {
+        let sidecars = |parallel_io: bool| {
+            let dir = tempfile::tempdir().unwrap();
+            for (name, num_frames) in [("a.swf", 1), ("b.swf", 2), ("c.swf", 3), ("d.swf", 4)] {
+                let data = create_test_swf(
+                    |header| header.num_frames = num_frames,
+                    &[swf::Tag::ShowFrame],
+                );
+                std::fs::write(dir.path().join(name), data).unwrap();
+            }
+            std::fs::write(dir.path().join("corrupt.swf"), b"not a swf file").unwrap();
+
+            // Trim the temporary directory so sidecars from both runs can be compared
+            let options = ExtractOptions {
+                trim_prefix: Some(dir.path().to_path_buf()),
+                ..Default::default()
+            };
+            let dir_options = DirectoryOptions {
+                parallel_io,
+                on_error: OnError::Placeholder,
+                ..Default::default()
+            };
+            let summary = process_directory(
+                &dir.path().to_path_buf(),
+                Format::Json,
+                &options,
+                &dir_options,
+                false,
+            );
+            assert_eq!(summary.completed, 5);
+
+            let mut sidecars: Vec<(String, String)> = std::fs::read_dir(dir.path())
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                .map(|path| {
+                    let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                    let content = std::fs::read_to_string(&path).unwrap();
+                    // Placeholders carry the untrimmed path; compare just their error
+                    (
+                        name,
+                        content.replace(&dir.path().to_string_lossy().into_owned(), ""),
+                    )
+                })
+                .collect();
+            sidecars.sort();
+            sidecars
+        };
+
+        let serial = sidecars(false);
+        assert_eq!(serial.len(), 5);
+        assert_eq!(sidecars(true), serial);
+    }
+
+    #[test]
+    fn test_filter_parse_errors() {
+        assert!(Filter::parse("frames >").is_err());
+        assert!(Filter::parse("depth > 3").is_err());
+        assert!(Filter::parse("(frames > 1").is_err());
+        assert!(Filter::parse("frames > 1 width == 2").is_err());
+        assert!(Filter::parse("!(frames >= 1) || frame_rate != 24").is_ok());
+    }
+
+    #[test]
+    fn test_output_dir_mirrors_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("assets");
+        for subdir in ["a", "b"] {
+            std::fs::create_dir_all(input.join(subdir)).unwrap();
+            let data = create_test_swf(|_| {}, &[swf::Tag::ShowFrame]);
+            std::fs::write(input.join(subdir).join("intro.swf"), data).unwrap();
+        }
+        let output = dir.path().join("catalog");
+        let dir_options = DirectoryOptions {
+            output_dir: Some(output.clone()),
+            ..Default::default()
+        };
+        process_directory(
+            &input,
+            Format::Json,
+            &ExtractOptions::default(),
+            &dir_options,
+            false,
+        );
+
+        assert!(output.join("a").join("intro.swf.json").exists());
+        assert!(output.join("b").join("intro.swf.json").exists());
+        assert!(!input.join("a").join("intro.swf.json").exists());
+    }
+
+    #[test]
+    fn test_checksum_sidecar_matches_digest() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = create_test_swf(|_| {}, &[swf::Tag::ShowFrame]);
+        std::fs::write(dir.path().join("a.swf"), data).unwrap();
+        let dir_options = DirectoryOptions {
+            checksum_sidecar: true,
+            ..Default::default()
+        };
+        process_directory(
+            &dir.path().to_path_buf(),
+            Format::Json,
+            &ExtractOptions::default(),
+            &dir_options,
+            false,
+        );
+
+        let sidecar = std::fs::read(dir.path().join("a.swf.json")).unwrap();
+        let checksum = std::fs::read_to_string(dir.path().join("a.swf.json.sha256")).unwrap();
+        assert_eq!(
+            checksum,
+            format!("{}  a.swf.json\n", to_hex(&Sha256::digest(&sidecar)))
+        );
+    }
+
+    #[test]
+    fn test_max_output_bytes_rejects_oversized_metadata() {
+        assert_eq!(
+            check_output_size("{\"file_name\":\"a.swf\"}", Some(10)),
+            Err("metadata is 21 bytes, over the --max-output-bytes limit of 10".to_string())
+        );
+        assert!(check_output_size("{}", Some(10)).is_ok());
+        assert!(check_output_size("{}", None).is_ok());
+
+        let run = |on_error| {
+            let dir = tempfile::tempdir().unwrap();
+            let data = create_test_swf(|_| {}, &[swf::Tag::ShowFrame]);
+            std::fs::write(dir.path().join("a.swf"), &data).unwrap();
+            std::fs::write(dir.path().join("b.swf"), &data).unwrap();
+            let dir_options = DirectoryOptions {
+                deterministic_order: true,
+                on_error,
+                max_output_bytes: Some(10),
+                ..Default::default()
+            };
+            let summary = process_directory(
+                &dir.path().to_path_buf(),
+                Format::Json,
+                &ExtractOptions::default(),
+                &dir_options,
+                false,
+            );
+            (dir, summary)
+        };
+
+        let (dir, summary) = run(OnError::Skip);
+        assert_eq!((summary.completed, summary.failed), (2, 2));
+        assert!(!dir.path().join("a.swf.json").exists());
+
+        // Stopping leaves b.swf untouched
+        let (_, summary) = run(OnError::Stop);
+        assert_eq!((summary.completed, summary.failed), (1, 1));
+
+        let (dir, summary) = run(OnError::Placeholder);
+        assert_eq!(summary.failed, 2);
+        let sidecar = std::fs::read_to_string(dir.path().join("a.swf.json")).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&sidecar).unwrap();
+        assert!(
+            parsed["error"]
+                .as_str()
+                .unwrap()
+                .contains("--max-output-bytes")
+        );
+    }
+
+    #[test]
+    fn test_since_hash_skips_unchanged_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("unchanged.swf"), b"same").unwrap();
+        std::fs::write(dir.path().join("modified.swf"), b"new contents").unwrap();
+        let manifest = dir.path().join("manifest.sha256");
+        std::fs::write(
+            &manifest,
+            format!(
+                "{}  ./unchanged.swf\n{} *modified.swf\n",
+                to_hex(&Sha256::digest(b"same")),
+                to_hex(&Sha256::digest(b"old contents"))
+            ),
+        )
+        .unwrap();
+
+        let hashes = load_hash_manifest(&manifest).unwrap();
+        let files = find_swf_files(&dir.path().to_path_buf(), true);
+        assert_eq!(
+            changed_files(files, dir.path(), &hashes, false),
+            vec![dir.path().join("modified.swf")]
+        );
+        assert!(load_hash_manifest(&dir.path().join("missing.sha256")).is_err());
+    }
+
+    #[test]
+    fn test_archive_root_hash() {
+        let root_hash = |files: &[(&str, &[u8])]| {
+            let dir = tempfile::tempdir().unwrap();
+            std::fs::create_dir(dir.path().join("sub")).unwrap();
+            for (name, contents) in files {
+                std::fs::write(dir.path().join(name), contents).unwrap();
+            }
+            let swf_files = find_swf_files(&dir.path().to_path_buf(), false);
+            archive_root_hash(dir.path(), &swf_files).unwrap()
+        };
+
+        let original = root_hash(&[
+            ("a.swf", b"one"),
+            ("b.swf", b"two"),
+            ("sub/c.swf", b"three"),
+        ]);
+        let copy = root_hash(&[
+            ("sub/c.swf", b"three"),
+            ("b.swf", b"two"),
+            ("a.swf", b"one"),
+        ]);
+        let modified = root_hash(&[("a.swf", b"one"), ("b.swf", b"2"), ("sub/c.swf", b"three")]);
+        assert_eq!(original, copy);
+        assert_ne!(original, modified);
+        assert_eq!(original.len(), 64);
+
+        // A file that disappeared since discovery is an error, not a panic
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("gone.swf");
+        assert!(archive_root_hash(dir.path(), &[missing]).is_err());
+    }
+
+    #[test]
+    fn test_on_error_skip_writes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("corrupt.swf"), b"not a swf file").unwrap();
 
-        // Should default to text format
-        assert!(result.contains("File: test.swf"));
-        assert!(result.contains("Stage Size: (800, 600)"));
+        process_directory(
+            &dir.path().to_path_buf(),
+            Format::Json,
+            &ExtractOptions::default(),
+            &DirectoryOptions::default(),
+            false,
+        );
+        assert!(!dir.path().join("corrupt.swf.json").exists());
     }
 }